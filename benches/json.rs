@@ -0,0 +1,46 @@
+//! Compare serde_json and simd-json on a large invite payload.
+//! Run with `cargo bench --features simd-json`.
+
+use discord_finder::discord::Invite;
+use std::time::{Duration, Instant};
+
+const ITERATIONS: u32 = 20;
+
+fn payload(invites: usize) -> String {
+    let invites: Vec<String> = (0..invites)
+        .map(|i| {
+            format!(
+                r#"{{"code": "code{i}", "guild": {{"id": "{i}", "name": "Server {i}", "description": "A server about things number {i}",
+                    "verification_level": 1, "features": ["NEWS", "COMMUNITY"]}}, "channel": {{"id": "{i}", "name": "general", "type": 0}},
+                    "inviter": {{"id": "{i}", "username": "user{i}", "avatar": null, "discriminator": "0001"}},
+                    "approximate_member_count": {members}, "approximate_presence_count": {online}}}"#,
+                i = i,
+                members = i * 10,
+                online = i * 3,
+            )
+        })
+        .collect();
+    format!("[{}]", invites.join(","))
+}
+
+fn measure(mut parse: impl FnMut() -> Vec<Invite>) -> Duration {
+    let start = Instant::now();
+    for _ in 0..ITERATIONS {
+        assert_eq!(parse().len(), 10_000);
+    }
+    start.elapsed() / ITERATIONS
+}
+
+fn main() {
+    let body = payload(10_000);
+    println!("payload: {} bytes", body.len());
+
+    let serde = measure(|| serde_json::from_str(&body).unwrap());
+    println!("serde_json: {:?} per parse", serde);
+
+    let simd = measure(|| {
+        let mut bytes = body.as_bytes().to_vec();
+        simd_json::serde::from_slice(&mut bytes).unwrap()
+    });
+    println!("simd-json:  {:?} per parse", simd);
+}
@@ -2,7 +2,8 @@
 //!   
 //! You can search google for every web page referring discord.gg in the last hour with google::search().  
 //! After you got these links, you can load the pages and parse them to get discord invite links with intermediary::resolve().  
-//! You can parse a discord invitation page with the Invite struct.
+//! You can parse a discord invitation page with the Invite struct.  
+//! [`find_servers`] does all of this in a single call.
 //!
 //! # Examples
 //!
@@ -19,61 +20,349 @@
 //! }
 //! ```
 
+use std::time::Duration;
+
 #[derive(Debug)]
 pub enum Error {
     Timeout,
     InvalidResponse,
+    Blocked,
+    ServerError(i32),
+    RateLimited(Duration),
+    NotCached,
+    ConsentRequired,
+    ServiceUnavailable,
+    ParseError,
+    ResponseTooLarge,
+    WidgetDisabled,
+    /// The host failed too many times in a row, see [`client::Client::with_circuit_breaker`].
+    CircuitOpen,
+    #[cfg(feature = "proxy")]
+    InvalidProxy,
 }
 
-/// Contains functions related to google pages parsing.
-pub mod google {
+/// Return every invite code referenced in a page, without duplicates.  
+/// This function does no I/O and never panics, which makes it a good target for fuzzers and property tests.
+/// See [`intermediary::extract_codes`].
+///
+/// # Examples
+///
+/// ```
+/// let codes = discord_finder::extract_all("<a href=\"https://discord.gg/UNWEj54\">Join</a>");
+/// assert_eq!(codes, vec!["UNWEj54".to_string()]);
+/// ```
+pub fn extract_all(html: &str) -> Vec<String> {
+    intermediary::extract_codes(html)
+}
+
+/// Search `pages` pages of Google results, resolve the invites they reference and fetch each of them,
+/// returning one invite per server. Fetches are paced (one per second) to respect the Discord API.  
+/// This is the "just give me servers" entry point, see [`crawler::Crawler`] to customize the crawl.
+///
+/// # Examples
+///
+/// ```no_run
+/// for invite in discord_finder::find_servers(2).unwrap() {
+///     println!("{}", invite);
+/// }
+/// ```
+pub fn find_servers(pages: usize) -> Result<Vec<discord::Invite>, Error> {
+    find_servers_with(crawler::Crawler::new(client::Client::new()), pages)
+}
+
+fn find_servers_with(mut crawler: crawler::Crawler, pages: usize) -> Result<Vec<discord::Invite>, Error> {
+    Ok(crawler.crawl(pages)?.into_iter().map(|found| found.invite).collect())
+}
+
+#[cfg(test)]
+mod mock {
     use super::Error;
-    use string_tools::{get_all_after, get_all_between_strict};
+    use std::cell::RefCell;
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+    use std::rc::Rc;
+    use std::sync::{Arc, Mutex};
+    use std::thread;
 
-    fn get_full_url(page: usize) -> String {
-        format!(
-            "https://www.google.com/search?q=\"discord.gg\"&tbs=qdr:h&filter=0&start={}",
-            page * 10
-        )
+    /// The pages searched by a search function produced by [`mock_search`].
+    pub type Searched = Rc<RefCell<Vec<usize>>>;
+
+    /// A local HTTP server answering every request with the response produced by a handler.
+    pub struct MockServer {
+        pub url: String,
+        requests: Arc<Mutex<Vec<String>>>,
     }
 
-    /// Search google for a something and returns result urls.  
-    /// See [Google Advanced Search](https://www.google.com/advanced_search) for more information about request syntax.  
-    /// Only one page is loaded.  
-    ///   
+    impl MockServer {
+        /// The handler receives the head of the request (request line and headers, with canonical
+        /// header names whatever the HTTP backend sent).
+        pub fn start<F>(handler: F) -> MockServer
+        where
+            F: Fn(&str) -> Vec<u8> + Send + 'static,
+        {
+            let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+            let url = format!("http://{}", listener.local_addr().unwrap());
+            let requests = Arc::new(Mutex::new(Vec::new()));
+            let received = Arc::clone(&requests);
+            thread::spawn(move || {
+                for mut stream in listener.incoming().flatten() {
+                    let mut head = Vec::new();
+                    let mut buffer = [0; 1024];
+                    while !head.ends_with(b"\r\n\r\n") {
+                        match stream.read(&mut buffer) {
+                            Ok(0) | Err(_) => break,
+                            Ok(n) => head.extend_from_slice(&buffer[..n]),
+                        }
+                    }
+                    let head = canonical_head(&String::from_utf8_lossy(&head));
+                    let response = handler(&head);
+                    received.lock().unwrap().push(head);
+                    let _ = stream.write_all(&response);
+                }
+            });
+            MockServer { url, requests }
+        }
+
+        /// The heads of the requests received so far.
+        pub fn requests(&self) -> Vec<String> {
+            self.requests.lock().unwrap().clone()
+        }
+    }
+
+    /// Write the header names of a request head as `Accept-Language`, since reqwest lowercases them.
+    fn canonical_head(head: &str) -> String {
+        let mut lines = head.split("\r\n");
+        let mut canonical = lines.next().unwrap_or_default().to_string();
+        for line in lines {
+            canonical.push_str("\r\n");
+            match line.split_once(':') {
+                Some((name, value)) => {
+                    let words: Vec<String> = name
+                        .split('-')
+                        .map(|word| {
+                            let mut chars = word.chars();
+                            match chars.next() {
+                                Some(first) => first.to_ascii_uppercase().to_string() + &chars.as_str().to_ascii_lowercase(),
+                                None => String::new(),
+                            }
+                        })
+                        .collect();
+                    canonical.push_str(&words.join("-"));
+                    canonical.push(':');
+                    canonical.push_str(value);
+                }
+                None => canonical.push_str(line),
+            }
+        }
+        canonical
+    }
+
+    pub fn http_response(status: u16, headers: &[(&str, &str)], body: &[u8]) -> Vec<u8> {
+        let mut response = format!("HTTP/1.1 {} Mock\r\nContent-Length: {}\r\nConnection: close\r\n", status, body.len());
+        for (name, value) in headers {
+            response.push_str(&format!("{}: {}\r\n", name, value));
+        }
+        response.push_str("\r\n");
+        let mut response = response.into_bytes();
+        response.extend_from_slice(body);
+        response
+    }
+
+    /// Serve result pages at `/{codes separated by +}` listing these invites, and answer the
+    /// invite API with `guild_of(code)` as guild id. Codes starting with "dead" do not exist.
+    pub fn mock_discord(guild_of: fn(&str) -> String) -> MockServer {
+        MockServer::start(move |request| {
+            let path = request.split(' ').nth(1).unwrap_or_default();
+            if let Some(code) = path.strip_prefix("/v6/invites/") {
+                let code = code.split('?').next().unwrap_or_default();
+                if code.starts_with("dead") {
+                    return http_response(404, &[], br#"{"message": "Unknown Invite", "code": 10006}"#);
+                }
+                let body = format!(
+                    r#"{{"code": "{}", "guild": {{"id": "{}", "name": "Server {}", "verification_level": 0}},
+                        "approximate_member_count": 10, "approximate_presence_count": 2}}"#,
+                    code,
+                    guild_of(code),
+                    code
+                );
+                http_response(200, &[], body.as_bytes())
+            } else {
+                let body: Vec<String> = path[1..].split('+').map(|code| format!("discord.gg/{}", code)).collect();
+                http_response(200, &[], body.join(" ").as_bytes())
+            }
+        })
+    }
+
+    /// A search function returning one result url per entry of `pages[page]`, and recording the searched pages.
+    pub fn mock_search(
+        server: &MockServer,
+        pages: Vec<Vec<&'static str>>,
+    ) -> (impl FnMut(usize) -> Result<Vec<String>, Error>, Searched) {
+        let url = server.url.clone();
+        let searched = Rc::new(RefCell::new(Vec::new()));
+        let record = Rc::clone(&searched);
+        let search = move |page: usize| {
+            record.borrow_mut().push(page);
+            Ok(pages
+                .get(page)
+                .map(|results| results.iter().map(|result| format!("{}/{}", url, result)).collect())
+                .unwrap_or_default())
+        };
+        (search, searched)
+    }
+
+    /// Deterministic pseudo-random bytes (xorshift), for fuzz-style tests.
+    pub fn random_bytes(seed: u64, len: usize) -> Vec<u8> {
+        let mut state = seed.wrapping_mul(0x9E37_79B9_7F4A_7C15) | 1;
+        (0..len)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                state as u8
+            })
+            .collect()
+    }
+
+    /// Random strings made of random bytes and of fragments close to what the parsers look for.
+    pub fn random_inputs() -> Vec<String> {
+        const FRAGMENTS: [&str; 13] = [
+            "https://discord.gg/",
+            "https://discord.com/invite/",
+            "discord.",
+            "gg/",
+            "é",
+            "€",
+            "-",
+            "\"r\"><a href=\"",
+            "\" onmousedown=\"return rwt(",
+            "href=\"/url?q=",
+            "&amp;",
+            "%",
+            "2021-03-05T14:02:11.1234567",
+        ];
+        (0..500)
+            .map(|seed| {
+                let bytes = random_bytes(seed, 1 + seed as usize % 64);
+                let mut input = String::new();
+                for byte in &bytes {
+                    if byte % 3 == 0 {
+                        input.push_str(FRAGMENTS[*byte as usize % FRAGMENTS.len()]);
+                    } else {
+                        input.push_str(&String::from_utf8_lossy(&[*byte]));
+                    }
+                }
+                input
+            })
+            .chain((0..100).map(|seed| String::from_utf8_lossy(&random_bytes(seed, 256)).into_owned()))
+            .collect()
+    }
+}
+
+/// Contains small helpers shared by the other modules.
+pub mod util {
+    use std::sync::Mutex;
+    use std::thread::sleep;
+    use std::time::{Duration, Instant};
+
+    /// Return the delay to wait before the retry number `attempt` (starting at 0).  
+    /// The delay doubles at each attempt, starting at `base`, and never exceeds `max`.
+    ///
     /// # Examples
-    ///   
+    ///
     /// ```
-    /// use discord_finder::google;
+    /// use discord_finder::util::backoff;
+    /// use std::time::Duration;
     ///
-    /// // note that we only test the first page of google results and that there can be more
-    /// let links = google::search(0).unwrap();
-    /// # assert!(!links.is_empty());
+    /// let delay = backoff(2, Duration::from_secs(1), Duration::from_secs(60));
+    /// assert_eq!(delay, Duration::from_secs(4));
     /// ```
-    pub fn search(page: usize) -> Result<Vec<String>, Error> {
-        if let Ok(response) = minreq::get(get_full_url(page))
-            .with_header("Accept", "text/plain")
-            .with_header("Host", "www.google.com")
-            .with_header(
-                "User-Agent",
-                "Mozilla/5.0 (X11; Linux x86_64; rv:71.0) Gecko/20100101 Firefox/71.0",
-            )
-            .send()
-        {
-            if let Ok(mut body) = response.as_str() {
-                let mut rep = Vec::new();
-                while let Some(url) =
-                    get_all_between_strict(body, "\"r\"><a href=\"", "\" onmousedown=\"return rwt(")
-                {
-                    rep.push(url.to_string());
-                    body = get_all_after(body, url);
-                }
-                Ok(rep)
-            } else {
-                Err(Error::InvalidResponse)
+    pub fn backoff(attempt: u32, base: Duration, max: Duration) -> Duration {
+        match 1u32.checked_shl(attempt).and_then(|factor| base.checked_mul(factor)) {
+            Some(delay) if delay < max => delay,
+            _ => max,
+        }
+    }
+
+    /// Return the lowercase host of an url, without credentials nor port.
+    pub(crate) fn host(url: &str) -> String {
+        let rest = url.split_once("://").map_or(url, |(_, rest)| rest);
+        let authority = rest.split(['/', '?', '#']).next().unwrap_or_default();
+        let host = authority.rsplit('@').next().unwrap_or_default();
+        host.split(':').next().unwrap_or_default().to_ascii_lowercase()
+    }
+
+    /// Normalize the url of a source page, so that equivalent urls are considered the same source:
+    /// the scheme and the host are lowercased, the leading `www.` and the trailing slash are removed,
+    /// and the fragment is dropped. The path and the query are kept as is.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use discord_finder::util::normalize_source_url;
+    ///
+    /// assert_eq!(normalize_source_url("https://www.Site.com/page/#top"), normalize_source_url("https://site.com/page"));
+    /// ```
+    pub fn normalize_source_url(url: &str) -> String {
+        let url = url.split('#').next().unwrap_or_default();
+        let (scheme, rest) = match url.split_once("://") {
+            Some((scheme, rest)) => (format!("{}://", scheme.to_ascii_lowercase()), rest),
+            None => (String::new(), url),
+        };
+        let (authority, path) = rest.split_at(rest.find(['/', '?']).unwrap_or(rest.len()));
+        let authority = authority.to_ascii_lowercase();
+        let authority = authority.strip_prefix("www.").unwrap_or(&authority);
+        let (path, query) = path.split_at(path.find('?').unwrap_or(path.len()));
+        format!("{}{}{}{}", scheme, authority, path.trim_end_matches('/'), query)
+    }
+
+    /// Return true if `host` is `domain` or one of its subdomains.
+    pub(crate) fn is_in_domain(host: &str, domain: &str) -> bool {
+        let domain = domain.trim_start_matches('.');
+        host.eq_ignore_ascii_case(domain)
+            || host
+                .len()
+                .checked_sub(domain.len() + 1)
+                .is_some_and(|dot| host.as_bytes()[dot] == b'.' && host[dot + 1..].eq_ignore_ascii_case(domain))
+    }
+
+    /// A token bucket shared by threads to keep their aggregated request rate under a limit.  
+    /// Tokens are refilled at `rate` per second, and at most `burst` of them can be saved up.
+    #[derive(Debug)]
+    pub struct TokenBucket {
+        rate: f64,
+        burst: f64,
+        /// available tokens, last refill
+        state: Mutex<(f64, Instant)>,
+    }
+
+    impl TokenBucket {
+        /// `rate` is expressed in tokens per second. The bucket starts full.
+        pub fn new(rate: f64, burst: u32) -> TokenBucket {
+            let burst = f64::from(burst.max(1));
+            TokenBucket {
+                rate: rate.max(f64::MIN_POSITIVE),
+                burst,
+                state: Mutex::new((burst, Instant::now())),
+            }
+        }
+
+        /// Take a token, waiting until one is available.
+        pub fn acquire(&self) {
+            loop {
+                let wait = {
+                    let mut state = self.state.lock().unwrap();
+                    let now = Instant::now();
+                    let tokens = (state.0 + now.duration_since(state.1).as_secs_f64() * self.rate).min(self.burst);
+                    *state = (tokens, now);
+                    if tokens >= 1.0 {
+                        state.0 -= 1.0;
+                        return;
+                    }
+                    Duration::from_secs_f64((1.0 - tokens) / self.rate)
+                };
+                sleep(wait);
             }
-        } else {
-            Err(Error::Timeout)
         }
     }
 
@@ -82,226 +371,6271 @@ pub mod google {
         use super::*;
 
         #[test]
-        fn get_full_url_test() {
-            assert_eq!(
-                "https://www.google.com/search?q=\"discord.gg\"&tbs=qdr:h&filter=0&start=10",
-                get_full_url(1)
-            );
+        fn token_bucket() {
+            let bucket = TokenBucket::new(50.0, 3);
+            let start = Instant::now();
+            for _ in 0..3 {
+                bucket.acquire();
+            }
+            assert!(start.elapsed() < Duration::from_millis(15));
+            for _ in 0..5 {
+                bucket.acquire();
+            }
+            assert!(start.elapsed() >= Duration::from_millis(95));
         }
-    }
-}
-
-pub mod intermediary {
-    use super::Error;
-    use super::discord::get_invite_code;
-    use string_tools::get_all_after;
 
-    /// put an url+noise, get url (without http://domain.something/)
-    fn get_url(url: &str) -> &str {
-        let mut i = 0;
-        for c in url.chars() {
-            // todo %20
-            if !c.is_ascii_alphanumeric() && c != '-' && c != '/' && c != '_' {
-                break;
+        #[test]
+        fn source_urls() {
+            let equivalent = [
+                ("https://site.com/page", "https://www.site.com/page/"),
+                ("https://Site.COM/page", "HTTPS://www.site.com/page#comments"),
+                ("https://site.com", "https://www.site.com/"),
+                ("https://site.com/page?id=1", "https://www.site.com/page/?id=1#top"),
+                ("site.com/page", "www.site.com/page/"),
+            ];
+            for (a, b) in equivalent.iter() {
+                assert_eq!(normalize_source_url(a), normalize_source_url(b), "{} and {}", a, b);
             }
-            i += 1;
+            assert_eq!(normalize_source_url("https://www.Site.com/Page/?Q=1#x"), "https://site.com/Page?Q=1");
+            // the path and the query are case-sensitive
+            assert_ne!(normalize_source_url("https://site.com/Page"), normalize_source_url("https://site.com/page"));
+            assert_ne!(normalize_source_url("https://site.com/?id=1"), normalize_source_url("https://site.com/?id=2"));
+            assert_ne!(normalize_source_url("https://wwwsite.com/"), normalize_source_url("https://site.com/"));
         }
-        &url[..i]
-    }
 
-    pub fn resolve(url: &str) -> Result<Vec<String>, Error> {
-        if let Ok(response) = minreq::get(url)
-            .with_header("Accept", "text/plain")
-            .with_header(
-                "User-Agent",
-                "Mozilla/5.0 (X11; Linux x86_64; rv:71.0) Gecko/20100101 Firefox/71.0",
-            )
-            .send()
-        {
-            if let Ok(mut body) = response.as_str() {
-                let mut rep = Vec::new();
-                // TODO discord.com
-                while get_all_after(&body, "discord.gg/") != "" {
-                    let url = get_url(get_all_after(&body, "discord.gg/"));
-                    body = get_all_after(&body, "discord.gg/");
-                    let url = if url.len() == 7 {
-                        format!("https://discord.com/invite/{}", url)
-                    } else {
-                        continue;
-                    };
-                    if !rep.contains(&url) {
-                        rep.push(url);
-                    }
-                }
-                Ok(rep)
-            } else {
-                Err(Error::InvalidResponse)
-            }
-        } else {
-            Err(Error::Timeout)
+        #[test]
+        fn backoff_sequence() {
+            let base = Duration::from_millis(100);
+            let max = Duration::from_secs(10);
+            let delays: Vec<u128> = (0..6).map(|i| backoff(i, base, max).as_millis()).collect();
+            assert_eq!(delays, vec![100, 200, 400, 800, 1600, 3200]);
+        }
+
+        #[test]
+        fn backoff_cap() {
+            let base = Duration::from_millis(100);
+            let max = Duration::from_secs(1);
+            assert_eq!(backoff(4, base, max), max);
+            assert_eq!(backoff(31, base, max), max);
+            assert_eq!(backoff(32, base, max), max);
+            assert_eq!(backoff(u32::MAX, base, max), max);
         }
     }
 }
 
-/// Contains discord fetcher
-pub mod discord {
+/// Contains functions related to google pages parsing.
+pub mod google {
+    use super::client::{Client, RawResponse};
+    use super::util::backoff;
     use super::Error;
-    use serde_json::{from_str, Value};
+    use log::{debug, trace};
+    use serde::{Deserialize, Serialize};
+    use std::collections::HashSet;
     use std::thread::sleep;
-    use std::time::{Duration, SystemTime, UNIX_EPOCH};
-    use string_tools::{get_all_between_strict, get_idx_between_strict};
+    use std::time::Duration;
+    use string_tools::{get_all_between, get_all_between_strict, get_idx_between_strict};
 
-    use serde::{Deserialize, Serialize};
+    const RETRY_BASE_DELAY: Duration = Duration::from_secs(1);
+    const RETRY_MAX_DELAY: Duration = Duration::from_secs(30);
 
-    /// Extract the id of the invitation from an url.
-    pub fn get_invite_code(url: &str) -> Option<&str> {
-        if url.len() > 27 && &url[0..27] == "https://discord.com/invite/" {
-            return Some(&url[27..]);
-        } else if url.len() > 19 && &url[0..19] == "https://discord.gg/" {
-            return Some(&url[19..]);
-        }
-        None
+    /// The number of results Google returns by default.
+    pub const DEFAULT_RESULTS_PER_PAGE: usize = 10;
+    /// The maximum number of results Google accepts to return in a single page.
+    pub const MAX_RESULTS_PER_PAGE: usize = 100;
+
+    fn get_full_url(page: usize) -> String {
+        get_full_url_with_limit(page, DEFAULT_RESULTS_PER_PAGE)
     }
 
-    #[derive(Debug, Serialize, Deserialize)]
-    pub struct Guild {
-        #[serde(skip_serializing_if = "Option::is_none")]
-        banner: Option<String>,
-        #[serde(skip_serializing_if = "Option::is_none")]
-        description: Option<String>,
-        id: String,
-        #[serde(skip_serializing_if = "Option::is_none")]
-        icon: Option<String>,
-        name: String,
-        #[serde(skip_serializing_if = "Option::is_none")]
-        splash: Option<String>,
-        #[serde(skip_serializing_if = "Option::is_none")]
-        vanity_url_code: Option<String>,
-        verification_level: u8,
+    fn get_full_url_with_limit(page: usize, results_per_page: usize) -> String {
+        SearchQueryBuilder::new()
+            .page(page)
+            .results_per_page(results_per_page)
+            .build()
     }
 
-    #[derive(Debug, Serialize, Deserialize)]
-    pub struct Channel {
-        id: String,
-        #[serde(skip_serializing_if = "Option::is_none")]
-        name: Option<String>,
-        r#type: usize,
+    /// Escape the characters that would break the query string.  
+    /// Quotes are kept as is, Google handles them fine.
+    fn encode_query(query: &str) -> String {
+        let mut encoded = String::with_capacity(query.len());
+        for c in query.chars() {
+            match c {
+                ' ' => encoded.push('+'),
+                '&' | '#' | '+' | '%' | '=' => encoded.push_str(&format!("%{:02X}", c as u8)),
+                c => encoded.push(c),
+            }
+        }
+        encoded
     }
 
-    #[derive(Debug, Serialize, Deserialize)]
-    pub struct User {
-        id: String,
-        username: String,
-        #[serde(skip_serializing_if = "Option::is_none")]
-        avatar: Option<String>,
-        discriminator: String,
+    /// Restrict the results to pages indexed recently.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum TimeWindow {
+        Hour,
+        Day,
+        Week,
+        Month,
+        Year,
     }
 
-    /// A simple struct used to store informations about a discord server invite link.
-    /// Can be serialized by activing the feature "serde-support"
-    #[derive(Debug, Serialize, Deserialize)]
-    pub struct Invite {
-        pub code: String,
-        #[serde(skip_serializing_if = "Option::is_none")]
-        pub guild: Option<Guild>,
-        pub channel: Channel,
-        #[serde(skip_serializing_if = "Option::is_none")]
-        pub inviter: Option<User>,
-        pub approximate_member_count: u64,
-        pub approximate_presence_count: u64,
+    impl TimeWindow {
+        fn as_tbs(self) -> &'static str {
+            match self {
+                TimeWindow::Hour => "qdr:h",
+                TimeWindow::Day => "qdr:d",
+                TimeWindow::Week => "qdr:w",
+                TimeWindow::Month => "qdr:m",
+                TimeWindow::Year => "qdr:y",
+            }
+        }
     }
 
-    impl Invite {
-        /// Loads a discord.gg page and produces an Invite struct.
-        pub fn fetch(url: &str) -> Result<Invite, Error> {
-            let invite_code = match get_invite_code(url) {
-                Some(code) => code,
-                None => return Err(Error::InvalidResponse),
-            };
-            let url = format!("https://discord.com/api/v6/invites/{}?with_counts=true", invite_code);
+    /// The kind of results to search for.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum Vertical {
+        /// Regular web pages.
+        Web,
+        /// Google News articles (`tbm=nws`), where invites are often fresher.
+        News,
+    }
 
-            if let Ok(response) = minreq::get(&url)
-                .with_header("Host", "discord.com")
-                .with_header(
-                    "User-Agent",
-                    "Mozilla/5.0 (X11; Linux x86_64; rv:72.0) Gecko/20100101 Firefox/72.0",
-                )
-                .with_header("Accept", "text/html")
-                .with_header("DNT", "1")
-                .with_header("Connection", "keep-alive")
-                .with_header("Upgrade-Insecure-Requests", "1")
-                .with_header("TE", "Trailers")
-                .send()
-            {
-                if response.status_code == 200 {
-                    if let Ok(body) = response.as_str() {
-                        println!("{}", body);
-    
-                        match from_str(body) {
-                            Ok(invite) => Ok(invite),
-                            Err(e) => {
-                                eprintln!("Parsing error: {:?}", e);
-                                Err(Error::InvalidResponse)
-                            }
-                        }
-                    } else {
-                        Err(Error::InvalidResponse)
-                    }
-                } else {
-                    Err(Error::InvalidResponse)
-                }
-            } else {
-                Err(Error::Timeout)
+    /// Build a google search url combining several operators.  
+    /// The default query is the one used by [`search`]: pages referring "discord.gg" in the last hour.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use discord_finder::google::{SearchQueryBuilder, TimeWindow};
+    ///
+    /// let url = SearchQueryBuilder::new()
+    ///     .site("reddit.com")
+    ///     .exclude("nsfw")
+    ///     .time_window(TimeWindow::Day)
+    ///     .locale("fr")
+    ///     .build();
+    /// assert_eq!(
+    ///     url,
+    ///     "https://www.google.com/search?q=\"discord.gg\"+site:reddit.com+-nsfw&tbs=qdr:d&filter=0&start=0&hl=fr"
+    /// );
+    /// ```
+    #[derive(Debug, Clone)]
+    pub struct SearchQueryBuilder {
+        terms: Vec<String>,
+        sites: Vec<String>,
+        exclusions: Vec<String>,
+        time_window: Option<TimeWindow>,
+        locale: Option<String>,
+        vertical: Vertical,
+        results_per_page: usize,
+        page: usize,
+        personalized: bool,
+        tag_results: bool,
+    }
+
+    impl Default for SearchQueryBuilder {
+        fn default() -> SearchQueryBuilder {
+            SearchQueryBuilder {
+                terms: vec!["\"discord.gg\"".to_string()],
+                sites: Vec::new(),
+                exclusions: Vec::new(),
+                time_window: Some(TimeWindow::Hour),
+                locale: None,
+                vertical: Vertical::Web,
+                results_per_page: DEFAULT_RESULTS_PER_PAGE,
+                page: 0,
+                personalized: true,
+                tag_results: false,
             }
         }
+    }
 
-        /// Return the url
-        pub fn get_url(&self) -> String {
-            format!("https://discord.com/invite/{}", self.code)
+    impl SearchQueryBuilder {
+        pub fn new() -> SearchQueryBuilder {
+            SearchQueryBuilder::default()
         }
-    }
 
-    #[cfg(test)]
-    mod tests {
-        use super::*;
+        /// Add a term that must appear in the results.
+        pub fn term(mut self, term: &str) -> SearchQueryBuilder {
+            self.terms.push(term.to_string());
+            self
+        }
 
-        #[test]
-        fn test_invite_struct() {
-            let invite =
-                Invite::fetch("https://discord.com/invite/seaofthievescommunity")
-                    .unwrap();
-            println!("{:#?}", invite);
+        /// Only return results from this site. Can be called several times to allow several sites.
+        pub fn site(mut self, domain: &str) -> SearchQueryBuilder {
+            self.sites.push(domain.to_string());
+            self
+        }
 
-            sleep(Duration::from_secs(5));
+        /// Exclude the results containing this word.
+        pub fn exclude(mut self, word: &str) -> SearchQueryBuilder {
+            self.exclusions.push(word.to_string());
+            self
+        }
 
-            let invite = Invite::fetch("https://discord.com/invite/UNWEj54").unwrap();
-            println!("{:#?}", invite);
+        pub fn time_window(mut self, time_window: TimeWindow) -> SearchQueryBuilder {
+            self.time_window = Some(time_window);
+            self
+        }
 
-            sleep(Duration::from_secs(5));
+        /// Remove the time restriction.
+        pub fn any_time(mut self) -> SearchQueryBuilder {
+            self.time_window = None;
+            self
+        }
 
-            let invite =
-            Invite::fetch("https://discord.gg/Yyakf3").unwrap();
-            println!("{:#?}", invite);
+        /// Set the interface language (for example "en" or "fr").
+        pub fn locale(mut self, locale: &str) -> SearchQueryBuilder {
+            self.locale = Some(locale.to_string());
+            self
         }
 
-        #[test]
-        fn get_invite_urls() {
-            assert_eq!(
-                get_invite_code("https://discord.com/invite/seaofthievescommunity"),
-                Some("seaofthievescommunity")
-            );
-            assert_eq!(
-                get_invite_code("https://discord.com/invite/UNWEj54"),
-                Some("UNWEj54")
-            );
-            assert_eq!(
-                get_invite_code("https://discord.gg/8j8b2xR"),
-                Some("8j8b2xR")
-            );
-            assert_eq!(
-                get_invite_code("https://discord.gg/Yyakf3"),
-                Some("Yyakf3")
-            );
+        /// Search the web (default) or Google News. Result pages of both are understood by [`parse_results`].
+        pub fn vertical(mut self, vertical: Vertical) -> SearchQueryBuilder {
+            self.vertical = vertical;
+            self
+        }
+
+        /// Clamped between 1 and [`MAX_RESULTS_PER_PAGE`].
+        pub fn results_per_page(mut self, results_per_page: usize) -> SearchQueryBuilder {
+            self.results_per_page = results_per_page.clamp(1, MAX_RESULTS_PER_PAGE);
+            self
+        }
+
+        pub fn page(mut self, page: usize) -> SearchQueryBuilder {
+            self.page = page;
+            self
+        }
+
+        /// Ask Google not to personalize the results (`pws=0`), so that successive crawls see the same results.
+        pub fn without_personalization(mut self) -> SearchQueryBuilder {
+            self.personalized = false;
+            self
+        }
+
+        /// Attach the query string to each [`SearchResult`] returned by [`search_detailed`], to know which
+        /// query produced which url when aggregating the results of several queries.
+        pub fn tag_results(mut self) -> SearchQueryBuilder {
+            self.tag_results = true;
+            self
+        }
+
+        /// Produce the query string, as typed in the search box.
+        pub fn query(&self) -> String {
+            let mut query = self.terms.join(" ");
+            let sites: Vec<String> = self.sites.iter().map(|site| format!("site:{}", site)).collect();
+            if !sites.is_empty() {
+                query.push(' ');
+                query.push_str(&sites.join(" OR "));
+            }
+            for exclusion in &self.exclusions {
+                query.push_str(&format!(" -{}", exclusion));
+            }
+            query
+        }
+
+        /// Produce the search url.
+        pub fn build(&self) -> String {
+            let mut url = format!("https://www.google.com/search?q={}", encode_query(&self.query()));
+            if self.vertical == Vertical::News {
+                url.push_str("&tbm=nws");
+            }
+            if let Some(time_window) = self.time_window {
+                url.push_str(&format!("&tbs={}", time_window.as_tbs()));
+            }
+            url.push_str(&format!("&filter=0&start={}", self.page * self.results_per_page));
+            if self.results_per_page != DEFAULT_RESULTS_PER_PAGE {
+                url.push_str(&format!("&num={}", self.results_per_page));
+            }
+            if let Some(locale) = &self.locale {
+                url.push_str(&format!("&hl={}", encode_query(locale)));
+            }
+            if !self.personalized {
+                url.push_str("&pws=0");
+            }
+            url
         }
     }
+
+    /// Search google for a something and returns result urls.  
+    /// See [Google Advanced Search](https://www.google.com/advanced_search) for more information about request syntax.  
+    /// Only one page is loaded.  
+    /// Fails with [`Error::ConsentRequired`] when Google shows its cookie consent page instead of the results.  
+    ///   
+    /// # Examples
+    ///   
+    /// `google::search(0)` loads the first page of results. It is the same as searching the default
+    /// [`SearchQueryBuilder`] with [`Client::search`], which can be run offline over a stub [`HttpClient`](crate::backend::HttpClient):
+    ///
+    /// ```
+    /// use discord_finder::backend::{HttpClient, HttpRequest};
+    /// use discord_finder::client::{Client, RawResponse};
+    /// use discord_finder::google::SearchQueryBuilder;
+    /// use discord_finder::Error;
+    /// use std::collections::HashMap;
+    ///
+    /// /// Serves the same result page to every request.
+    /// #[derive(Debug)]
+    /// struct CannedPage;
+    ///
+    /// impl HttpClient for CannedPage {
+    ///     fn get(&self, request: &HttpRequest) -> Result<RawResponse, Error> {
+    ///         let body = r#"<div class="r"><a href="https://disboard.org/servers/tag/gaming" onmousedown="return rwt(this)">Gaming</a></div>
+    ///                       <div class="r"><a href="https://top.gg/servers" onmousedown="return rwt(this)">Top</a></div>"#;
+    ///         Ok(RawResponse { status_code: 200, headers: HashMap::new(), url: request.url.to_string(), body: body.into() })
+    ///     }
+    /// }
+    ///
+    /// let client = Client::new().with_http_client(CannedPage);
+    /// let links = client.search(&SearchQueryBuilder::new().page(0)).unwrap();
+    /// assert_eq!(links, vec!["https://disboard.org/servers/tag/gaming", "https://top.gg/servers"]);
+    /// ```
+    ///
+    /// The result links are extracted with [`parse_results`], which can be tested offline.
+    pub fn search(page: usize) -> Result<Vec<String>, Error> {
+        search_url(&Client::new(), &get_full_url(page))
+    }
+
+    /// Same as [`search`] but asks Google for `results_per_page` results per page instead of 10,
+    /// reducing the number of pages to load.  
+    /// The value is clamped between 1 and [`MAX_RESULTS_PER_PAGE`].
+    pub fn search_with_limit(page: usize, results_per_page: usize) -> Result<Vec<String>, Error> {
+        search_url(&Client::new(), &get_full_url_with_limit(page, results_per_page))
+    }
+
+    /// Same as [`search`] but with a custom query.  
+    /// See also [`Client::search`] to use the settings of a client.
+    pub fn search_query(query: &SearchQueryBuilder) -> Result<Vec<String>, Error> {
+        search_url(&Client::new(), &query.build())
+    }
+
+    /// A search result, see [`search_detailed`].
+    #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+    pub struct SearchResult {
+        pub url: String,
+        /// The query that produced the result, if it was built with [`SearchQueryBuilder::tag_results`].
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub query: Option<String>,
+    }
+
+    /// Same as [`search_query`] but returns [`SearchResult`]s, tagged with the query if it was built with
+    /// [`SearchQueryBuilder::tag_results`].
+    pub fn search_detailed(query: &SearchQueryBuilder) -> Result<Vec<SearchResult>, Error> {
+        search_detailed_url(&Client::new(), query, &query.build())
+    }
+
+    fn search_detailed_url(client: &Client, query: &SearchQueryBuilder, url: &str) -> Result<Vec<SearchResult>, Error> {
+        let tag = if query.tag_results { Some(query.query()) } else { None };
+        Ok(search_url(client, url)?
+            .into_iter()
+            .map(|url| SearchResult {
+                url,
+                query: tag.clone(),
+            })
+            .collect())
+    }
+
+    /// Load the `page` of the default query once per time window and merge the results, without
+    /// duplicates, in the order they were found.  
+    /// For example `[TimeWindow::Hour, TimeWindow::Day]` catches both fresh and slightly older invites.
+    pub fn search_windows(page: usize, windows: &[TimeWindow]) -> Result<Vec<String>, Error> {
+        let urls: Vec<String> = windows
+            .iter()
+            .map(|window| SearchQueryBuilder::new().time_window(*window).page(page).build())
+            .collect();
+        search_merged(&Client::new(), &urls)
+    }
+
+    fn search_merged(client: &Client, urls: &[String]) -> Result<Vec<String>, Error> {
+        let mut seen = HashSet::new();
+        let mut results = Vec::new();
+        for url in urls {
+            for result in search_url(client, url)? {
+                if seen.insert(result.clone()) {
+                    results.push(result);
+                }
+            }
+        }
+        Ok(results)
+    }
+
+    /// Load a result page, failing if Google blocked the request.
+    fn load_page(client: &Client, url: &str) -> Result<RawResponse, Error> {
+        let response = client.request(url, &[("Accept", "text/plain"), ("Host", "www.google.com")])?;
+        if response.status_code == 429 || response.url.contains("/sorry/") {
+            debug!("google blocked the search {}", url);
+            return Err(Error::Blocked);
+        }
+        if response.status_code >= 500 {
+            return Err(Error::ServerError(response.status_code));
+        }
+        Ok(response)
+    }
+
+    pub(crate) fn search_url(client: &Client, url: &str) -> Result<Vec<String>, Error> {
+        let response = load_page(client, url)?;
+        if let Some(body) = response.body_str() {
+            let mut results = parse_page(&response.url, body)?;
+            results.retain(|result| client.accepts_domain(result));
+            trace!("{} results found on {}", results.len(), url);
+            Ok(results)
+        } else {
+            Err(Error::InvalidResponse)
+        }
+    }
+
+    /// Load a result page of the default query (as [`search`]) and return the "related searches" Google suggests
+    /// at the bottom of the page. They can seed further queries, with [`SearchQueryBuilder::term`] for example.
+    pub fn search_related(page: usize) -> Result<Vec<String>, Error> {
+        related_url(&Client::new(), &get_full_url(page))
+    }
+
+    fn related_url(client: &Client, url: &str) -> Result<Vec<String>, Error> {
+        let response = load_page(client, url)?;
+        if get_all_between(&response.url, "://", "/").starts_with("consent.") {
+            return Err(Error::ConsentRequired);
+        }
+        match response.body_str() {
+            Some(body) => Ok(parse_related(body)),
+            None => Err(Error::InvalidResponse),
+        }
+    }
+
+    /// Extract the "related searches" suggestions of a google result page, without any network activity.  
+    /// They are the links to other queries: the links to other pages, verticals or filters of the same query
+    /// and the spelling corrections are ignored.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use discord_finder::google::parse_related;
+    ///
+    /// let page = r#"<a href="/search?q=discord+gg+gaming&amp;sa=X">discord gg <b>gaming</b></a>"#;
+    /// assert_eq!(parse_related(page), vec!["discord gg gaming".to_string()]);
+    /// ```
+    pub fn parse_related(mut body: &str) -> Vec<String> {
+        let decode = |query: &str| decode_url(&query.replace('+', " "));
+        // the query of the page, in the value of the search box
+        let current = body.find("name=\"q\"").and_then(|idx| {
+            let tag_start = body[..idx].rfind('<')?;
+            let tag_end = body[idx..].find('>').map(|i| idx + i)?;
+            let value = get_all_between_strict(&body[tag_start..tag_end], "value=\"", "\"")?;
+            Some(value.replace("&quot;", "\"").replace("&amp;", "&"))
+        });
+        let mut related = Vec::new();
+        while let Some((href, rest)) = next_between(body, "href=\"/search?", "\"") {
+            body = rest;
+            let href = href.replace("&amp;", "&");
+            let mut query = None;
+            let mut same_query = false;
+            for (name, value) in href.split('&').filter_map(|param| param.split_once('=')) {
+                match name {
+                    "q" => query = Some(decode(value)),
+                    "start" | "tbm" | "tbs" | "udm" | "spell" => same_query = true,
+                    _ => (),
+                }
+            }
+            if let Some(query) = query.filter(|query| !query.trim().is_empty()) {
+                if !same_query && current.as_ref() != Some(&query) && !related.contains(&query) {
+                    related.push(query);
+                }
+            }
+        }
+        related
+    }
+
+    /// Parse a result page, detecting the cookie consent interstitial Google shows in the EU instead of results.
+    fn parse_page(url: &str, body: &str) -> Result<Vec<String>, Error> {
+        if get_all_between(url, "://", "/").starts_with("consent.") {
+            return Err(Error::ConsentRequired);
+        }
+        let results = parse_results(body);
+        if results.is_empty() && body.contains("https://consent.google.") {
+            return Err(Error::ConsentRequired);
+        }
+        Ok(results)
+    }
+
+    /// Extract the result urls from a google result page, without any network activity.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use discord_finder::{google, intermediary};
+    ///
+    /// let page = r#"<div class="r"><a href="https://example.com/servers" onmousedown="return rwt(this)">Servers</a></div>"#;
+    /// let links = google::parse_results(page);
+    /// assert_eq!(links, vec!["https://example.com/servers".to_string()]);
+    ///
+    /// // then each link would be loaded with intermediary::resolve(), which relies on extract_codes()
+    /// let codes = intermediary::extract_codes("<p>Join us: https://discord.gg/UNWEj54</p>");
+    /// assert_eq!(codes, vec!["UNWEj54".to_string()]);
+    /// ```
+    pub fn parse_results(body: &str) -> Vec<String> {
+        parse_results_with_strategy(body).0
+    }
+
+    /// The way result urls were found in a google page.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum Strategy {
+        /// The links of the regular result page.
+        Primary,
+        /// The links of a Google News result page.
+        News,
+        /// The `/url?q=` redirection links served to basic clients.
+        Fallback,
+        /// Nothing matched: Google probably changed its layout.
+        NoMatch,
+    }
+
+    /// Same as [`parse_results`] but also tells which strategy produced the results,
+    /// which helps diagnosing Google layout changes.
+    pub fn parse_results_with_strategy(body: &str) -> (Vec<String>, Strategy) {
+        let results = parse_primary(body);
+        if !results.is_empty() {
+            return (results, Strategy::Primary);
+        }
+        let results = parse_news(body);
+        if !results.is_empty() {
+            return (results, Strategy::News);
+        }
+        let results = parse_fallback(body);
+        if !results.is_empty() {
+            return (results, Strategy::Fallback);
+        }
+        (Vec::new(), Strategy::NoMatch)
+    }
+
+    /// Return the text between `begin` and `end` and the text following `end`.  
+    /// The search always moves forward, even when the text between the markers is empty.
+    fn next_between<'a>(body: &'a str, begin: &str, end: &str) -> Option<(&'a str, &'a str)> {
+        let (start, stop) = get_idx_between_strict(body, begin, end)?;
+        Some((&body[start..stop], &body[stop + end.len()..]))
+    }
+
+    fn parse_primary(mut body: &str) -> Vec<String> {
+        let mut rep = Vec::new();
+        while let Some((url, rest)) = next_between(body, "\"r\"><a href=\"", "\" onmousedown=\"return rwt(") {
+            if !url.is_empty() {
+                rep.push(url.to_string());
+            }
+            body = rest;
+        }
+        rep
+    }
+
+    /// News results are `<a class="WlydOe" href="...">` links wrapping the article card.
+    fn parse_news(mut body: &str) -> Vec<String> {
+        let mut rep = Vec::new();
+        while let Some(idx) = body.find("class=\"WlydOe\"") {
+            let tag_start = body[..idx].rfind('<').unwrap_or(0);
+            let tag_end = body[idx..].find('>').map(|i| idx + i).unwrap_or(body.len());
+            if let Some(url) = get_all_between_strict(&body[tag_start..tag_end], "href=\"", "\"") {
+                let url = url.replace("&amp;", "&");
+                if url.starts_with("http") && !rep.contains(&url) {
+                    rep.push(url);
+                }
+            }
+            body = &body[tag_end..];
+        }
+        rep
+    }
+
+    fn parse_fallback(mut body: &str) -> Vec<String> {
+        let mut rep = Vec::new();
+        while let Some((url, rest)) = next_between(body, "href=\"/url?q=", "&amp;") {
+            body = rest;
+            let url = decode_url(url);
+            let host = get_all_between(&url, "://", "/");
+            if url.starts_with("http") && !host.contains("google.") && !rep.contains(&url) {
+                rep.push(url);
+            }
+        }
+        rep
+    }
+
+    /// Decode the `%XX` sequences of an url.
+    fn decode_url(url: &str) -> String {
+        let bytes = url.as_bytes();
+        let mut decoded = Vec::with_capacity(bytes.len());
+        let mut i = 0;
+        while i < bytes.len() {
+            if bytes[i] == b'%' {
+                let byte = bytes
+                    .get(i + 1..i + 3)
+                    .and_then(|hex| std::str::from_utf8(hex).ok())
+                    .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+                if let Some(byte) = byte {
+                    decoded.push(byte);
+                    i += 3;
+                    continue;
+                }
+            }
+            decoded.push(bytes[i]);
+            i += 1;
+        }
+        String::from_utf8_lossy(&decoded).into_owned()
+    }
+
+    /// Same as [`search`] but retries up to `max_retries` times on timeouts and server errors,
+    /// waiting longer after each failure.  
+    /// Fails immediately if Google blocked the request (CAPTCHA), since retrying would only make it worse.
+    pub fn search_with_retry(page: usize, max_retries: u32) -> Result<Vec<String>, Error> {
+        retry(max_retries, RETRY_BASE_DELAY, || search(page))
+    }
+
+    fn retry<T, F>(max_retries: u32, base_delay: Duration, mut request: F) -> Result<T, Error>
+    where
+        F: FnMut() -> Result<T, Error>,
+    {
+        let mut attempt = 0;
+        loop {
+            match request() {
+                Err(Error::Timeout) | Err(Error::ServerError(_)) if attempt < max_retries => {
+                    sleep(backoff(attempt, base_delay, RETRY_MAX_DELAY));
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn retry_after_transient_failure() {
+            let mut calls = 0;
+            let result = retry(3, Duration::from_millis(1), || {
+                calls += 1;
+                if calls == 1 {
+                    Err(Error::Timeout)
+                } else {
+                    Ok(vec!["https://example.com/".to_string()])
+                }
+            });
+            assert_eq!(result.unwrap(), vec!["https://example.com/".to_string()]);
+            assert_eq!(calls, 2);
+        }
+
+        #[test]
+        fn retry_gives_up() {
+            let mut calls = 0;
+            let result: Result<(), Error> = retry(2, Duration::from_millis(1), || {
+                calls += 1;
+                Err(Error::ServerError(503))
+            });
+            assert!(matches!(result, Err(Error::ServerError(503))));
+            assert_eq!(calls, 3);
+        }
+
+        #[test]
+        fn no_retry_when_blocked() {
+            let mut calls = 0;
+            let result: Result<(), Error> = retry(5, Duration::from_millis(1), || {
+                calls += 1;
+                Err(Error::Blocked)
+            });
+            assert!(matches!(result, Err(Error::Blocked)));
+            assert_eq!(calls, 1);
+        }
+
+        #[test]
+        fn primary_strategy() {
+            let body = r#"<div class="r"><a href="https://example.com/a" onmousedown="return rwt(this)">A</a></div>
+                <div class="r"><a href="https://example.org/b" onmousedown="return rwt(this)">B</a></div>"#;
+            assert_eq!(
+                parse_results_with_strategy(body),
+                (
+                    vec!["https://example.com/a".to_string(), "https://example.org/b".to_string()],
+                    Strategy::Primary
+                )
+            );
+        }
+
+        #[test]
+        fn news_strategy() {
+            let body = r#"<div id="rso"><div class="SoaBEf" data-hveid="CAEQAA"><div class="xuvV6b BGxR7d">
+                <a jsname="YKoRaf" class="WlydOe" href="https://www.gamesradar.com/best-discord-servers/" data-ved="2ahUKEwj" ping="/url?sa=t">
+                <div class="SoAPf"><div class="MgUUmf NUnG9d"><span>GamesRadar+</span></div>
+                <div class="n0jPhd ynAwRc MBeuO nDgy9d" role="heading" aria-level="3">The best Discord servers to join</div>
+                <div class="GI74Re nDgy9d">Looking for a community? Here are our picks...</div>
+                <div class="OSrXXb rbYSKb LfVVr"><span>3 hours ago</span></div></div></a></div></div>
+                <div class="SoaBEf" data-hveid="CAIQAA"><div class="xuvV6b BGxR7d">
+                <a jsname="YKoRaf" class="WlydOe" href="https://www.pcgamer.com/servers/?ref=news&amp;page=1" data-ved="2ahUKEwk">
+                <div class="n0jPhd ynAwRc MBeuO nDgy9d" role="heading" aria-level="3">Discord communities worth a look</div></a>
+                </div></div></div>
+                <a class="fl" href="/search?q=%22discord.gg%22&amp;tbm=nws&amp;start=10">2</a>"#;
+            assert_eq!(
+                parse_results_with_strategy(body),
+                (
+                    vec![
+                        "https://www.gamesradar.com/best-discord-servers/".to_string(),
+                        "https://www.pcgamer.com/servers/?ref=news&page=1".to_string()
+                    ],
+                    Strategy::News
+                )
+            );
+
+            let url = SearchQueryBuilder::new().vertical(Vertical::News).build();
+            assert_eq!(url, "https://www.google.com/search?q=\"discord.gg\"&tbm=nws&tbs=qdr:h&filter=0&start=0");
+        }
+
+        #[test]
+        fn fallback_strategy() {
+            let body = r#"<a href="/url?q=https://example.com/servers%3Fpage%3D2&amp;sa=U&amp;ved=x">Servers</a>
+                <a href="/url?q=https://maps.google.com/&amp;sa=U">Maps</a>
+                <a href="/url?q=https://example.com/servers%3Fpage%3D2&amp;sa=U&amp;ved=y">Servers</a>
+                <a href="/url?q=https://example.org/&amp;sa=U">Other</a>"#;
+            assert_eq!(
+                parse_results_with_strategy(body),
+                (
+                    vec!["https://example.com/servers?page=2".to_string(), "https://example.org/".to_string()],
+                    Strategy::Fallback
+                )
+            );
+        }
+
+        #[test]
+        fn tagged_results() {
+            let server = crate::mock::MockServer::start(|_| {
+                crate::mock::http_response(200, &[], br#"<div class="r"><a href="https://example.com/a" onmousedown="return rwt(this)">x</a></div>"#)
+            });
+            let query = SearchQueryBuilder::new().site("reddit.com");
+            let results = search_detailed_url(&Client::new(), &query, &server.url).unwrap();
+            assert_eq!(results[0].query, None);
+
+            let query = query.tag_results();
+            assert_eq!(query.query(), "\"discord.gg\" site:reddit.com");
+            assert_eq!(
+                search_detailed_url(&Client::new(), &query, &server.url).unwrap(),
+                vec![SearchResult {
+                    url: "https://example.com/a".to_string(),
+                    query: Some("\"discord.gg\" site:reddit.com".to_string()),
+                }]
+            );
+        }
+
+        #[test]
+        fn related_searches() {
+            let page = r#"<html><head><title>&quot;discord.gg&quot; - Google Search</title></head><body>
+                <form action="/search"><input class="noHIxc" value="&quot;discord.gg&quot;" name="q" type="text"></form>
+                <a href="/search?q=%22discord.gg%22&amp;tbm=isch&amp;sa=X">Images</a>
+                <a href="/search?q=%22discord.gg%22&amp;tbs=qdr:d&amp;sa=X">Past 24 hours</a>
+                <a href="/search?q=%22discord.gg%22&amp;sa=X&amp;ved=0ahUKEwi">"discord.gg"</a>
+                <div class="r"><a href="https://example.com/a" onmousedown="return rwt(this)">x</a></div>
+                <div id="botstuff"><div class="card-section"><div class="brs_col">
+                <p class="nVcaUb"><a href="/search?ie=UTF-8&amp;q=discord+gg+anime&amp;sa=X&amp;ved=2ahUKEwj">discord gg <b>anime</b></a></p>
+                <p class="nVcaUb"><a href="/search?ie=UTF-8&amp;q=discord.gg+fortnite+servers&amp;sa=X">discord.gg <b>fortnite servers</b></a></p>
+                <p class="nVcaUb"><a href="/search?ie=UTF-8&amp;q=discord+gg+caf%C3%A9&amp;sa=X">discord gg <b>café</b></a></p>
+                <p class="nVcaUb"><a href="/search?ie=UTF-8&amp;q=discord+gg+anime&amp;sa=X&amp;ved=3">discord gg <b>anime</b></a></p>
+                </div></div></div>
+                <a href="/search?q=%22discord.gg%22&amp;start=10&amp;sa=N">Next</a>
+                </body></html>"#;
+            assert_eq!(parse_related(page), vec!["discord gg anime", "discord.gg fortnite servers", "discord gg café"]);
+            assert!(parse_related("<p>No results</p>").is_empty());
+            for input in crate::mock::random_inputs() {
+                let _ = parse_related(&input);
+            }
+        }
+
+        #[test]
+        fn merged_windows() {
+            let server = crate::mock::MockServer::start(|request| {
+                let results: &[&str] = if request.contains("tbs=qdr:h") {
+                    &["https://example.com/a", "https://example.com/b"]
+                } else {
+                    &["https://example.com/b", "https://example.com/c", "https://example.com/a"]
+                };
+                let body: Vec<String> = results
+                    .iter()
+                    .map(|url| format!(r#"<div class="r"><a href="{}" onmousedown="return rwt(this)">x</a></div>"#, url))
+                    .collect();
+                crate::mock::http_response(200, &[], body.concat().as_bytes())
+            });
+            let urls = vec![format!("{}/search?tbs=qdr:h", server.url), format!("{}/search?tbs=qdr:d", server.url)];
+            assert_eq!(
+                search_merged(&Client::new(), &urls).unwrap(),
+                vec!["https://example.com/a", "https://example.com/b", "https://example.com/c"]
+            );
+            assert_eq!(server.requests().len(), 2);
+        }
+
+        #[test]
+        fn consent_page() {
+            let body = r#"<!DOCTYPE html><html lang="fr"><head><title>Avant d'accéder à Google</title></head>
+                <body><div class="box"><h1>Avant d'accéder à Google</h1>
+                <p>Nous utilisons des cookies et des données pour fournir et gérer nos services.</p>
+                <form action="https://consent.google.com/save" method="POST">
+                <input type="hidden" name="gl" value="FR"><input type="hidden" name="continue"
+                value="https://www.google.com/search?q=%22discord.gg%22&amp;tbs=qdr:h">
+                <button>Tout accepter</button></form></div></body></html>"#;
+            assert!(matches!(
+                parse_page("https://www.google.com/search?q=x", body),
+                Err(Error::ConsentRequired)
+            ));
+            assert!(matches!(
+                parse_page("https://consent.google.com/ml?continue=https://www.google.com/search", ""),
+                Err(Error::ConsentRequired)
+            ));
+
+            // a regular page linking to the consent settings is not an interstitial
+            let body = r#"<div class="r"><a href="https://example.com/a" onmousedown="return rwt(this)">A</a></div>
+                <a href="https://consent.google.com/dl">Privacy</a>"#;
+            assert_eq!(
+                parse_page("https://www.google.com/search?q=x", body).unwrap(),
+                vec!["https://example.com/a".to_string()]
+            );
+        }
+
+        #[test]
+        fn no_strategy() {
+            assert_eq!(
+                parse_results_with_strategy("<html>Our systems have detected unusual traffic</html>"),
+                (Vec::new(), Strategy::NoMatch)
+            );
+        }
+
+        #[test]
+        fn parse_adversarial_pages() {
+            // empty urls used to make the parsers loop forever
+            let page = r#""r"><a href="" onmousedown="return rwt(this)" href="/url?q=&amp;"#;
+            assert_eq!(parse_results_with_strategy(page), (Vec::new(), Strategy::NoMatch));
+            assert_eq!(parse_results("href=\"/url?q=https://example.com/%E2%8&amp;"), vec!["https://example.com/\u{FFFD}%8"]);
+
+            for input in crate::mock::random_inputs() {
+                parse_results_with_strategy(&input);
+                let _ = parse_page(&input, &input);
+            }
+        }
+
+        #[test]
+        fn get_full_url_test() {
+            assert_eq!(
+                "https://www.google.com/search?q=\"discord.gg\"&tbs=qdr:h&filter=0&start=10",
+                get_full_url(1)
+            );
+        }
+
+        #[test]
+        fn results_per_page() {
+            assert_eq!(
+                "https://www.google.com/search?q=\"discord.gg\"&tbs=qdr:h&filter=0&start=100&num=50",
+                get_full_url_with_limit(2, 50)
+            );
+            assert_eq!(
+                "https://www.google.com/search?q=\"discord.gg\"&tbs=qdr:h&filter=0&start=100&num=100",
+                get_full_url_with_limit(1, 500)
+            );
+            assert_eq!(get_full_url(3), get_full_url_with_limit(3, DEFAULT_RESULTS_PER_PAGE));
+        }
+
+        #[test]
+        fn query_builder() {
+            let url = SearchQueryBuilder::new()
+                .term("server")
+                .site("reddit.com")
+                .site("twitter.com")
+                .exclude("spam")
+                .exclude("nsfw")
+                .time_window(TimeWindow::Week)
+                .locale("en")
+                .results_per_page(20)
+                .page(2)
+                .build();
+            assert_eq!(
+                url,
+                "https://www.google.com/search?q=\"discord.gg\"+server+site:reddit.com+OR+site:twitter.com+-spam+-nsfw&tbs=qdr:w&filter=0&start=40&num=20&hl=en"
+            );
+
+            let url = SearchQueryBuilder::new().term("C++ & Rust").any_time().build();
+            assert_eq!(
+                url,
+                "https://www.google.com/search?q=\"discord.gg\"+C%2B%2B+%26+Rust&filter=0&start=0"
+            );
+        }
+
+        #[test]
+        fn without_personalization() {
+            let url = SearchQueryBuilder::new().locale("en").without_personalization().build();
+            assert_eq!(
+                url,
+                "https://www.google.com/search?q=\"discord.gg\"&tbs=qdr:h&filter=0&start=0&hl=en&pws=0"
+            );
+            assert!(!SearchQueryBuilder::new().build().contains("pws"));
+        }
+    }
+}
+
+pub mod intermediary {
+    use super::discord::{get_invite_code, is_valid_code, is_zero_width, INVITE_CODE_LENGTH};
+    use super::client::Client;
+    use super::util::{host, is_in_domain};
+    use super::Error;
+    use serde_json::Value;
+    use std::collections::HashSet;
+    use std::io::BufRead;
+    use std::ops::Add;
+    use std::thread::sleep;
+    use std::time::Duration;
+    use string_tools::{get_all_after, get_all_between_strict, get_idx_between_strict};
+
+    /// put an url+noise, get url (without http://domain.something/)
+    fn get_url(url: &str) -> &str {
+        // todo %20
+        let end = url
+            .find(|c: char| !c.is_ascii_alphanumeric() && c != '-' && c != '/' && c != '_')
+            .unwrap_or(url.len());
+        &url[..end]
+    }
+
+    /// Collect the `content` of every `og:url` meta tag.
+    fn og_urls(mut body: &str) -> Vec<&str> {
+        let mut urls = Vec::new();
+        while let Some(idx) = body.find("og:url") {
+            let tag_start = body[..idx].rfind('<').unwrap_or(0);
+            let tag_end = body[idx..].find('>').map(|i| idx + i).unwrap_or(body.len());
+            if let Some(content) = get_all_between_strict(&body[tag_start..tag_end], "content=\"", "\"") {
+                urls.push(content);
+            }
+            body = &body[tag_end..];
+        }
+        urls
+    }
+
+    /// Return the absolute url of the first `<link rel="canonical">` tag, if any.
+    fn canonical_url(page_url: &str, mut body: &str) -> Option<String> {
+        while let Some(idx) = body.find("rel=\"canonical\"") {
+            let tag_start = body[..idx].rfind('<').unwrap_or(0);
+            let tag_end = body[idx..].find('>').map(|i| idx + i).unwrap_or(body.len());
+            let tag = &body[tag_start..tag_end];
+            if tag.starts_with("<link") {
+                let href = get_all_between_strict(tag, "href=\"", "\"")?.replace("&amp;", "&");
+                if href.starts_with("http://") || href.starts_with("https://") {
+                    return Some(href);
+                }
+                // a root-relative url, relative to the origin of the page
+                let scheme_end = page_url.find("://")? + 3;
+                let origin_end = page_url[scheme_end..].find('/').map_or(page_url.len(), |i| scheme_end + i);
+                return href.starts_with('/').then(|| format!("{}{}", &page_url[..origin_end], href));
+            }
+            body = &body[tag_end..];
+        }
+        None
+    }
+
+    /// Collect every string value of every JSON-LD block.
+    fn json_ld_strings(mut body: &str) -> Vec<String> {
+        fn walk(value: Value, strings: &mut Vec<String>) {
+            match value {
+                Value::String(string) => strings.push(string),
+                Value::Array(values) => values.into_iter().for_each(|v| walk(v, strings)),
+                Value::Object(map) => map.into_iter().for_each(|(_, v)| walk(v, strings)),
+                _ => (),
+            }
+        }
+
+        let mut strings = Vec::new();
+        while let Some((start, end)) = get_idx_between_strict(body, "application/ld+json", "</script>") {
+            if let Ok(value) = serde_json::from_str(get_all_after(&body[start..end], ">")) {
+                walk(value, &mut strings);
+            }
+            body = &body[end..];
+        }
+        strings
+    }
+
+    /// Extract every invite code referenced in a web page, without duplicates and in discovery order.  
+    /// Invites declared in `og:url` meta tags and JSON-LD blocks come first, followed by every `discord.gg/` and
+    /// `discord.com/invite/` mention, whatever their case.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use discord_finder::intermediary::extract_codes;
+    ///
+    /// let codes = extract_codes("Join us on discord.gg/UNWEj54!");
+    /// assert_eq!(codes, vec!["UNWEj54".to_string()]);
+    /// ```
+    pub fn extract_codes(body: &str) -> Vec<String> {
+        scan(body).codes
+    }
+
+    /// Same as [`extract_codes`] but also returns the byte offset where each code starts in the page,
+    /// to correlate the invites with their context.  
+    /// The offset of a code found in a JSON-LD block is the one of its first mention.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use discord_finder::intermediary::extract_codes_with_offsets;
+    ///
+    /// let codes = extract_codes_with_offsets("Join us on discord.gg/UNWEj54!");
+    /// assert_eq!(codes, vec![("UNWEj54".to_string(), 22)]);
+    /// ```
+    pub fn extract_codes_with_offsets(body: &str) -> Vec<(String, usize)> {
+        let scan = scan(body);
+        scan.codes.into_iter().zip(scan.offsets).collect()
+    }
+
+    /// Remove the backslashes escaping ASCII punctuation, as in `discord\.gg/abc\_def`.
+    fn unescape_markdown(md: &str) -> String {
+        let mut unescaped = String::with_capacity(md.len());
+        let mut chars = md.chars().peekable();
+        while let Some(c) = chars.next() {
+            if c == '\\' && chars.peek().is_some_and(char::is_ascii_punctuation) {
+                continue;
+            }
+            unescaped.push(c);
+        }
+        unescaped
+    }
+
+    /// Split a Markdown document into its link targets (inline links and reference definitions)
+    /// and the remaining text, from which the links were removed.
+    fn markdown_links(md: &str) -> (Vec<&str>, String) {
+        let mut targets = Vec::new();
+        let mut text = String::with_capacity(md.len());
+        for line in md.lines() {
+            let trimmed = line.trim_start();
+            // a reference definition: [label]: url "title"
+            if let Some((label, definition)) = trimmed.strip_prefix('[').and_then(|rest| rest.split_once("]:")) {
+                if !label.contains(']') {
+                    if let Some(target) = definition.split_whitespace().next() {
+                        targets.push(target.trim_start_matches('<').trim_end_matches('>'));
+                    }
+                    continue;
+                }
+            }
+
+            let mut rest = line;
+            while let Some(open) = rest.find('[') {
+                let close = match rest[open..].find(']') {
+                    Some(close) => open + close,
+                    None => break,
+                };
+                let after = &rest[close + 1..];
+                if let Some(destination) = after.strip_prefix('(') {
+                    // an inline link: [text](url "title"), the text is dropped
+                    let end = destination.find(')').unwrap_or(destination.len());
+                    if let Some(target) = destination[..end].split_whitespace().next() {
+                        targets.push(target.trim_start_matches('<').trim_end_matches('>'));
+                    }
+                    text.push_str(&rest[..open]);
+                    rest = destination.get(end + 1..).unwrap_or_default();
+                } else if after.starts_with('[') {
+                    // a reference link: [text][label], whose target is defined elsewhere
+                    let end = after.find(']').map_or(after.len(), |i| i + 1);
+                    text.push_str(&rest[..open]);
+                    rest = &after[end..];
+                } else {
+                    text.push_str(&rest[..=close]);
+                    rest = after;
+                }
+            }
+            text.push_str(rest);
+            text.push('\n');
+        }
+        (targets, text)
+    }
+
+    /// Extract the invite codes of a Markdown document (a Reddit post or a forum message), without duplicates.  
+    /// The targets of inline links (`[text](https://discord.gg/x)`) and of reference-style links
+    /// (`[text][1]` and `[1]: https://discord.gg/x`) come first, followed by the autolinks and the bare links.
+    /// Link texts are ignored, since they can display another invite than the one they lead to.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use discord_finder::intermediary::extract_from_markdown;
+    ///
+    /// let codes = extract_from_markdown("Join [discord.gg/fake](https://discord.gg/UNWEj54)!");
+    /// assert_eq!(codes, vec!["UNWEj54".to_string()]);
+    /// ```
+    pub fn extract_from_markdown(md: &str) -> Vec<String> {
+        let md = unescape_markdown(md);
+        let (targets, text) = markdown_links(&md);
+        let mut codes: Vec<String> = Vec::new();
+        for code in targets.iter().flat_map(|target| extract_codes(target)).chain(extract_codes(&text)) {
+            if !codes.contains(&code) {
+                codes.push(code);
+            }
+        }
+        codes
+    }
+
+    /// Same as [`extract_codes`] for the text produced by OCR or PDF-to-text tools, where links are often
+    /// broken across lines (`discord.gg/ab\ncde`): the line breaks inside the codes are removed before extraction.  
+    /// A code at the end of a line is joined to the word starting the next line, so prefer [`extract_codes`]
+    /// for regular pages.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use discord_finder::intermediary::extract_from_plain_text;
+    ///
+    /// assert_eq!(extract_from_plain_text("Join discord.gg/UNW\nEj54 !"), vec!["UNWEj54".to_string()]);
+    /// ```
+    pub fn extract_from_plain_text(text: &str) -> Vec<String> {
+        extract_codes(&join_broken_codes(text))
+    }
+
+    /// Remove the whitespace containing a single line break from the codes following an invite link prefix.
+    fn join_broken_codes(text: &str) -> String {
+        let is_code_char = |c: char| c.is_ascii_alphanumeric() || c == '-' || c == '_';
+        let lowercase = text.to_ascii_lowercase();
+        let mut joined = String::with_capacity(text.len());
+        let mut position = 0;
+        while let Some(idx) = lowercase[position..].find("discord.") {
+            let prefix_end = ["discord.gg/", "discord.com/invite/"]
+                .iter()
+                .find(|prefix| lowercase[position + idx..].starts_with(*prefix))
+                .map(|prefix| position + idx + prefix.len());
+            let mut end = match prefix_end {
+                Some(end) => end,
+                None => {
+                    joined.push_str(&text[position..position + idx + "discord.".len()]);
+                    position += idx + "discord.".len();
+                    continue;
+                }
+            };
+            joined.push_str(&text[position..end]);
+            loop {
+                let code_len = text[end..].find(|c: char| !is_code_char(c)).unwrap_or(text.len() - end);
+                joined.push_str(&text[end..end + code_len]);
+                end += code_len;
+                let space_len = text[end..].find(|c: char| !c.is_whitespace()).unwrap_or(text.len() - end);
+                let next = text[end + space_len..].chars().next();
+                // a blank line ends the paragraph, and the code
+                let line_breaks = text[end..end + space_len].matches('\n').count();
+                if line_breaks == 1 && next.is_some_and(is_code_char) {
+                    end += space_len;
+                } else {
+                    break;
+                }
+            }
+            position = end;
+        }
+        joined.push_str(&text[position..]);
+        joined
+    }
+
+    /// Why a candidate found after an invite link prefix was not considered as an invite code.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum Reason {
+        TooShort,
+        TooLong,
+        InvalidCharacters,
+    }
+
+    /// A candidate skipped during the resolution.
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub struct SkipReason {
+        pub raw: String,
+        pub reason: Reason,
+    }
+
+    fn skip_reason(code: &str) -> Option<Reason> {
+        if code.len() < *INVITE_CODE_LENGTH.start() {
+            Some(Reason::TooShort)
+        } else if code.len() > *INVITE_CODE_LENGTH.end() {
+            Some(Reason::TooLong)
+        } else if !is_valid_code(code) {
+            Some(Reason::InvalidCharacters)
+        } else {
+            None
+        }
+    }
+
+    struct Scan {
+        codes: Vec<String>,
+        /// The byte offset of each code in the page
+        offsets: Vec<usize>,
+        /// The number of invite link mentions
+        hits: usize,
+        skipped: Vec<SkipReason>,
+    }
+
+    /// Return the offset of `part` in `whole`, which must be a subslice of it.
+    fn offset_in(whole: &str, part: &str) -> usize {
+        part.as_ptr() as usize - whole.as_ptr() as usize
+    }
+
+    fn scan(body: &str) -> Scan {
+        let mut codes: Vec<String> = Vec::new();
+        let mut offsets = Vec::new();
+        let mut skipped = Vec::new();
+        // every candidate already processed, valid or not, so that repeated mentions are skipped at once
+        let mut seen: HashSet<String> = HashSet::new();
+        let mut push = |code: &str, offset: usize| {
+            if !seen.contains(code) {
+                seen.insert(code.to_string());
+                match skip_reason(code) {
+                    None => {
+                        codes.push(code.to_string());
+                        offsets.push(offset);
+                    }
+                    Some(reason) => skipped.push(SkipReason {
+                        raw: code.to_string(),
+                        reason,
+                    }),
+                }
+            }
+        };
+
+        for url in og_urls(body) {
+            if let Some(code) = get_invite_code(url) {
+                push(get_url(code), offset_in(body, code));
+            }
+        }
+        for string in json_ld_strings(body) {
+            if let Some(code) = get_invite_code(&string) {
+                // JSON strings are decoded, so the code is located by searching it
+                let code = get_url(code);
+                push(code, body.find(code).unwrap_or_default());
+            }
+        }
+
+        // ascii lowercasing keeps byte offsets, so positions found in one string are valid in the other
+        let lowercase = body.to_ascii_lowercase();
+        let mut hits = 0;
+        let mut position = 0;
+        while let Some(idx) = lowercase[position..].find("discord.") {
+            position += idx + "discord.".len();
+            if lowercase[position..].starts_with("gg/") {
+                position += "gg/".len();
+            } else if lowercase[position..].starts_with("com/invite/") {
+                position += "com/invite/".len();
+            } else {
+                continue;
+            }
+            hits += 1;
+            // invisible characters hidden after the prefix (regular spaces end the link)
+            position = body.len() - body[position..].trim_start_matches(is_zero_width).len();
+            let start = position;
+            let mut code = get_url(&body[position..]);
+            position += code.len();
+            // "join discord.gg/abc123- it's great": the dash is punctuation, not part of the code
+            let followed_by_space = body[position..].chars().next().map(char::is_whitespace).unwrap_or(true);
+            if followed_by_space && (code.ends_with('-') || code.ends_with('_')) {
+                code = &code[..code.len() - 1];
+            }
+            push(code, start);
+        }
+        Scan {
+            codes,
+            offsets,
+            hits,
+            skipped,
+        }
+    }
+
+    fn to_invite_urls(codes: Vec<String>) -> Vec<String> {
+        codes
+            .into_iter()
+            .map(|code| format!("https://discord.com/invite/{}", code))
+            .collect()
+    }
+
+    /// Sites listing community servers, whose pages almost always contain invites.
+    const COMMUNITY_SITES: [&str; 9] = [
+        "disboard.org",
+        "top.gg",
+        "discord.me",
+        "discords.com",
+        "discordservers.com",
+        "discordhome.com",
+        "discadia.com",
+        "discordlist.gg",
+        "reddit.com",
+    ];
+
+    /// Guess, without loading it, whether a page is likely to reference invites: it belongs to a known server
+    /// list or community site, or its path mentions "discord", "server" or "invite".  
+    /// This cheap heuristic is used to resolve the most promising results first.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use discord_finder::intermediary::looks_promising;
+    ///
+    /// assert!(looks_promising("https://disboard.org/servers/tag/gaming"));
+    /// assert!(!looks_promising("https://example.com/blog/cooking"));
+    /// ```
+    pub fn looks_promising(url: &str) -> bool {
+        let url = url.to_ascii_lowercase();
+        let rest = url.split_once("://").map_or(url.as_str(), |(_, rest)| rest);
+        let path = rest.find(['/', '?', '#']).map_or("", |i| &rest[i..]);
+        let path = path.split(['?', '#']).next().unwrap_or_default();
+        let host = host(&url);
+        let known_site = COMMUNITY_SITES.iter().any(|site| is_in_domain(&host, site));
+        known_site || ["discord", "server", "invite"].iter().any(|word| path.contains(word))
+    }
+
+    /// Load a page and return the invite links it references.  
+    /// See also [`Client::resolve`] to use the settings of a client.
+    pub fn resolve(url: &str) -> Result<Vec<String>, Error> {
+        resolve_with(&Client::new(), url)
+    }
+
+    /// Same as [`resolve`] but the invite links are sorted lexicographically instead of in order of discovery,
+    /// which makes the output stable when a page is reorganized.
+    pub fn resolve_sorted(url: &str) -> Result<Vec<String>, Error> {
+        let mut invites = resolve_with(&Client::new(), url)?;
+        invites.sort();
+        Ok(invites)
+    }
+
+    /// Rewrite the url of an AMP or mobile page to the url of its desktop version, which sometimes
+    /// references more invites: the `m.`, `mobile.` and `amp.` subdomains, the `amp` path segments and the
+    /// Google AMP cache (`https://www.google.com/amp/s/...`) are removed. Other urls are returned unchanged.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use discord_finder::intermediary::desktop_url;
+    ///
+    /// assert_eq!(desktop_url("https://m.example.com/amp/servers/"), "https://example.com/servers/");
+    /// ```
+    pub fn desktop_url(url: &str) -> String {
+        let (scheme, rest) = match url.split_once("://") {
+            Some(parts) => parts,
+            None => return url.to_string(),
+        };
+        let rest = match rest.strip_prefix("www.google.com/amp/") {
+            Some(cached) => match cached.strip_prefix("s/") {
+                Some(cached) => return desktop_url(&format!("https://{}", cached)),
+                None => return desktop_url(&format!("http://{}", cached)),
+            },
+            None => rest,
+        };
+        let (host, path) = rest.find(['/', '?', '#']).map_or((rest, ""), |i| rest.split_at(i));
+        let host = ["m.", "mobile.", "amp."]
+            .iter()
+            .find_map(|prefix| host.strip_prefix(prefix).filter(|host| host.contains('.')))
+            .unwrap_or(host);
+        let (path, query) = path.find(['?', '#']).map_or((path, ""), |i| path.split_at(i));
+        let mut segments: Vec<&str> = path.split('/').filter(|segment| *segment != "amp").collect();
+        if segments.len() == 1 && path.starts_with('/') {
+            segments.push("");
+        }
+        format!("{}://{}{}{}", scheme, host, segments.join("/"), query)
+    }
+
+    pub(crate) fn resolve_with(client: &Client, url: &str) -> Result<Vec<String>, Error> {
+        let desktop;
+        let url = if client.desktop_urls {
+            desktop = desktop_url(url);
+            desktop.as_str()
+        } else {
+            url
+        };
+        let body = load(client, url)?;
+        let codes = extract_codes(&body);
+        if codes.is_empty() && client.follow_canonical {
+            // only one hop, and never back to the same page
+            if let Some(canonical) = canonical_url(url, &body).filter(|canonical| canonical != url) {
+                return load(client, &canonical).map(|body| to_invite_urls(extract_codes(&body)));
+            }
+        }
+        Ok(to_invite_urls(codes))
+    }
+
+    /// Same as [`resolve`] but checks each invite with the Discord API and only returns the ones that exist,
+    /// which removes the false positives of the text scan at the cost of one request per invite.  
+    /// `delay` is waited between two API requests. Network errors and rate limits are returned as errors.  
+    /// See also [`Client::resolve_validated`] to use the settings of a client.
+    pub fn resolve_validated(url: &str, delay: Duration) -> Result<Vec<String>, Error> {
+        resolve_validated_with(&Client::new(), url, delay)
+    }
+
+    pub(crate) fn resolve_validated_with(client: &Client, url: &str, delay: Duration) -> Result<Vec<String>, Error> {
+        let mut validated = Vec::new();
+        for (i, invite_link) in resolve_with(client, url)?.into_iter().enumerate() {
+            if i > 0 {
+                sleep(delay);
+            }
+            if client.fetch_invite_opt(&invite_link)?.is_some() {
+                validated.push(invite_link);
+            }
+        }
+        Ok(validated)
+    }
+
+    /// Statistics about the invites referenced by a page.
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub struct ResolveStats {
+        /// The invite links, as returned by [`resolve`].
+        pub invites: Vec<String>,
+        /// The number of `discord.gg/` and `discord.com/invite/` mentions, including duplicates and invalid codes.
+        pub total_hits: usize,
+        /// The number of unique valid codes.
+        pub unique: usize,
+    }
+
+    impl ResolveStats {
+        /// Combine the statistics of another page (or crawl) into these ones.  
+        /// Hits are summed while invites found in both are only counted once.
+        pub fn merge(&mut self, other: ResolveStats) {
+            let mut known: HashSet<String> = self.invites.iter().cloned().collect();
+            for invite in other.invites {
+                if known.insert(invite.clone()) {
+                    self.invites.push(invite);
+                }
+            }
+            self.total_hits += other.total_hits;
+            self.unique = self.invites.len();
+        }
+
+        fn from_body(body: &str) -> ResolveStats {
+            let scan = scan(body);
+            ResolveStats {
+                unique: scan.codes.len(),
+                invites: to_invite_urls(scan.codes),
+                total_hits: scan.hits,
+            }
+        }
+    }
+
+    impl Add for ResolveStats {
+        type Output = ResolveStats;
+
+        fn add(mut self, other: ResolveStats) -> ResolveStats {
+            self.merge(other);
+            self
+        }
+    }
+
+    /// Same as [`resolve`] but also counts the raw mentions, which helps spotting pages spamming links.
+    pub fn resolve_stats(url: &str) -> Result<ResolveStats, Error> {
+        load(&Client::new(), url).map(|body| ResolveStats::from_body(&body))
+    }
+
+    /// Same as [`resolve`] but returns the invite codes with the byte offset where they were found in the page
+    /// (see [`extract_codes_with_offsets`]).  
+    /// Canonical links are not followed, since the offsets would refer to another page.
+    pub fn resolve_with_offsets(url: &str) -> Result<Vec<(String, usize)>, Error> {
+        load(&Client::new(), url).map(|body| extract_codes_with_offsets(&body))
+    }
+
+    /// Same as [`resolve`] but also reports the candidates that were skipped and why, which helps debugging crawls.
+    pub fn resolve_verbose(url: &str) -> Result<(Vec<String>, Vec<SkipReason>), Error> {
+        load(&Client::new(), url).map(|body| {
+            let scan = scan(&body);
+            (to_invite_urls(scan.codes), scan.skipped)
+        })
+    }
+
+    fn load(client: &Client, url: &str) -> Result<String, Error> {
+        let response = client.request(url, &[("Accept", "text/plain")])?;
+        // invite links are ascii, a few invalid bytes elsewhere in the page must not hide them
+        Ok(String::from_utf8_lossy(&response.body).into_owned())
+    }
+
+    /// The maximum number of pages of a sitemap resolved by [`resolve_sitemap`].
+    pub const MAX_SITEMAP_PAGES: usize = 500;
+
+    /// Return the urls listed in the `<loc>` tags of a sitemap.
+    fn sitemap_urls(body: &str) -> Vec<String> {
+        let mut urls = Vec::new();
+        let mut rest = body;
+        while let Some(start) = rest.find("<loc>") {
+            rest = &rest[start + 5..];
+            let end = match rest.find("</loc>") {
+                Some(end) => end,
+                None => break,
+            };
+            let url = rest[..end].trim().replace("&amp;", "&");
+            if !url.is_empty() {
+                urls.push(url);
+            }
+            rest = &rest[end + 6..];
+        }
+        urls
+    }
+
+    /// Resolve every page listed in a sitemap (up to [`MAX_SITEMAP_PAGES`]) and return
+    /// the invite links found, without duplicates.
+    pub fn resolve_sitemap(sitemap_url: &str) -> Result<Vec<String>, Error> {
+        resolve_sitemap_with_limit(sitemap_url, MAX_SITEMAP_PAGES)
+    }
+
+    /// Same as [`resolve_sitemap`] but resolves at most `max_pages` pages.  
+    /// Pages that cannot be loaded are skipped.
+    pub fn resolve_sitemap_with_limit(sitemap_url: &str, max_pages: usize) -> Result<Vec<String>, Error> {
+        let client = Client::new();
+        let sitemap = load(&client, sitemap_url)?;
+        let mut seen = HashSet::new();
+        let mut invites = Vec::new();
+        for url in sitemap_urls(&sitemap).into_iter().take(max_pages) {
+            if let Ok(links) = resolve_with(&client, &url) {
+                invites.extend(links.into_iter().filter(|link| seen.insert(link.clone())));
+            }
+        }
+        Ok(invites)
+    }
+
+    /// Read one url per line and lazily resolve each of them, which makes it easy to pipe urls to a program.  
+    /// Empty lines are skipped and reading stops at the first I/O error.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use discord_finder::intermediary::resolve_reader;
+    ///
+    /// for invite_links in resolve_reader(std::io::stdin().lock()) {
+    ///     println!("{:?}", invite_links);
+    /// }
+    /// ```
+    pub fn resolve_reader<R: BufRead>(reader: R) -> impl Iterator<Item = Result<Vec<String>, Error>> {
+        reader
+            .lines()
+            .map_while(Result::ok)
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| resolve(line.trim()))
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use crate::mock::{http_response, MockServer};
+        use std::io::Cursor;
+
+        #[test]
+        fn promising_urls() {
+            assert!(looks_promising("https://disboard.org/servers/tag/gaming"));
+            assert!(looks_promising("https://www.reddit.com/r/rust/comments/abc/"));
+            assert!(looks_promising("https://TOP.GG/"));
+            assert!(looks_promising("https://example.com/best-Discord-communities"));
+            assert!(looks_promising("http://example.com/servers?page=2"));
+            assert!(looks_promising("https://example.com/join/invite"));
+            assert!(!looks_promising("https://example.com/blog/cooking"));
+            assert!(!looks_promising("https://notreddit.com/"));
+            assert!(!looks_promising("https://discord-fans.example.com/"));
+            assert!(!looks_promising("https://example.com/?q=server"));
+            assert!(!looks_promising(""));
+        }
+
+        #[test]
+        fn desktop_urls() {
+            assert_eq!(desktop_url("https://example.com/news/servers/amp/"), "https://example.com/news/servers/");
+            assert_eq!(desktop_url("https://example.com/amp/news/servers"), "https://example.com/news/servers");
+            assert_eq!(desktop_url("https://example.com/amp"), "https://example.com/");
+            assert_eq!(desktop_url("https://m.example.com/servers?page=2"), "https://example.com/servers?page=2");
+            assert_eq!(desktop_url("http://amp.example.co.uk/a/amp/b#top"), "http://example.co.uk/a/b#top");
+            assert_eq!(
+                desktop_url("https://www.google.com/amp/s/m.example.com/servers/amp"),
+                "https://example.com/servers"
+            );
+            // only whole segments and subdomains are rewritten
+            assert_eq!(desktop_url("https://m.com/example/ampere"), "https://m.com/example/ampere");
+            assert_eq!(desktop_url("https://example.com/?q=amp"), "https://example.com/?q=amp");
+            assert_eq!(desktop_url("not an url"), "not an url");
+
+            let server = MockServer::start(|request| {
+                let path = request.split(' ').nth(1).unwrap_or_default();
+                match path {
+                    "/article" => http_response(200, &[], b"discord.gg/UNWEj54 discord.gg/8j8b2xR"),
+                    _ => http_response(200, &[], b"discord.gg/UNWEj54"),
+                }
+            });
+            let amp = format!("{}/amp/article", server.url);
+            assert_eq!(Client::new().resolve(&amp).unwrap().len(), 1);
+            assert_eq!(Client::new().with_desktop_urls(true).resolve(&amp).unwrap().len(), 2);
+        }
+
+        #[test]
+        fn follow_canonical() {
+            let server = MockServer::start(|request| {
+                let path = request.split(' ').nth(1).unwrap_or_default();
+                match path {
+                    "/amp" => http_response(200, &[], br#"<html><head><link rel="canonical" href="/article"></head></html>"#),
+                    "/article" => http_response(200, &[], b"<p>Join discord.gg/UNWEj54</p>"),
+                    "/loop" => http_response(200, &[], br#"<link href="/loop" rel="canonical">"#),
+                    "/first" => http_response(200, &[], br#"<link rel="canonical" href="/second">"#),
+                    "/second" => http_response(200, &[], br#"<link rel="canonical" href="/article">"#),
+                    _ => http_response(404, &[], b""),
+                }
+            });
+            let client = Client::new();
+            assert!(client.resolve(&format!("{}/amp", server.url)).unwrap().is_empty());
+
+            let client = Client::new().with_follow_canonical(true);
+            assert_eq!(
+                client.resolve(&format!("{}/amp", server.url)).unwrap(),
+                vec!["https://discord.com/invite/UNWEj54"]
+            );
+            assert!(client.resolve(&format!("{}/loop", server.url)).unwrap().is_empty());
+            assert!(client.resolve(&format!("{}/first", server.url)).unwrap().is_empty());
+            let requests: Vec<String> = server.requests().iter().map(|r| r.split(' ').nth(1).unwrap().to_string()).collect();
+            assert_eq!(requests, vec!["/amp", "/amp", "/article", "/loop", "/first", "/second"]);
+        }
+
+        #[test]
+        fn validated_invites() {
+            let server = crate::mock::mock_discord(|code| code.to_string());
+            let client = Client::new().with_api_base(&server.url);
+            let page = format!("{}/aaaa+deadbeef+bbbb+dead2", server.url);
+            assert_eq!(client.resolve(&page).unwrap().len(), 4);
+            assert_eq!(
+                client.resolve_validated(&page, Duration::from_millis(10)).unwrap(),
+                vec!["https://discord.com/invite/aaaa", "https://discord.com/invite/bbbb"]
+            );
+            let fetched = server.requests().iter().filter(|r| r.starts_with("GET /v6/invites/")).count();
+            assert_eq!(fetched, 4);
+        }
+
+        #[test]
+        fn zero_width_characters() {
+            let page = "Join discord.gg/\u{200B}UNWEj54\u{200B} or <meta property=\"og:url\" content=\"https://discord.gg/\u{FEFF}8j8b2xR \">";
+            assert_eq!(extract_codes(page), vec!["8j8b2xR", "UNWEj54"]);
+            assert_eq!(extract_codes_with_offsets("discord.gg/\u{200B}abcd")[0].1, "discord.gg/\u{200B}".len());
+            // the case is kept, and regular spaces still end the link
+            assert_eq!(extract_codes("discord.gg/\u{2060}AbCd discord.gg/ is"), vec!["AbCd"]);
+        }
+
+        #[test]
+        fn broken_lines() {
+            let text = "Our server: discord.gg/ab\ncdef, the old one:\nhttps://discord.com/invite/\r\n  UNW \n Ej54\n\nThanks";
+            assert_eq!(extract_codes(text), vec!["ab"]);
+            assert_eq!(extract_from_plain_text(text), vec!["abcdef", "UNWEj54"]);
+            // spaces on the same line still end the code
+            assert_eq!(extract_from_plain_text("discord.gg/abcd efgh discord.x discord.gg/"), vec!["abcd"]);
+            for input in crate::mock::random_inputs() {
+                let _ = extract_from_plain_text(&input);
+            }
+        }
+
+        #[test]
+        fn large_pages() {
+            let mut page = "<li><a href=\"https://example.com/\">A server about nothing</a></li>\n".repeat(60_000);
+            page.push_str("discord.gg/UNWEj54 <a href=\"https://discord.gg/lastone\">Join</a></body></html>");
+            assert!(page.len() > 4_000_000);
+            let chunked = page.clone();
+            let server = MockServer::start(move |request| {
+                if request.starts_with("GET /chunked ") {
+                    let mut response = b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\nConnection: close\r\n\r\n".to_vec();
+                    for chunk in chunked.as_bytes().chunks(65_000) {
+                        response.extend_from_slice(format!("{:x}\r\n", chunk.len()).as_bytes());
+                        response.extend_from_slice(chunk);
+                        response.extend_from_slice(b"\r\n");
+                    }
+                    response.extend_from_slice(b"0\r\n\r\n");
+                    response
+                } else {
+                    http_response(200, &[], page.as_bytes())
+                }
+            });
+            let expected = vec!["https://discord.com/invite/UNWEj54", "https://discord.com/invite/lastone"];
+            assert_eq!(resolve(&server.url).unwrap(), expected);
+            assert_eq!(resolve(&format!("{}/chunked", server.url)).unwrap(), expected);
+        }
+
+        #[test]
+        fn sorted_invites() {
+            let server = MockServer::start(|_| http_response(200, &[], b"discord.gg/zzzz discord.gg/UNWEj54 discord.gg/abcd"));
+            assert_eq!(
+                resolve(&server.url).unwrap(),
+                vec![
+                    "https://discord.com/invite/zzzz",
+                    "https://discord.com/invite/UNWEj54",
+                    "https://discord.com/invite/abcd",
+                ]
+            );
+            assert_eq!(
+                resolve_sorted(&server.url).unwrap(),
+                vec![
+                    "https://discord.com/invite/UNWEj54",
+                    "https://discord.com/invite/abcd",
+                    "https://discord.com/invite/zzzz",
+                ]
+            );
+        }
+
+        #[test]
+        fn sitemap() {
+            let server = MockServer::start(move |request| {
+                let path = request.split(' ').nth(1).unwrap_or_default();
+                match path {
+                    "/sitemap.xml" => {
+                        let port = request.split("Host: 127.0.0.1:").nth(1).unwrap_or_default();
+                        let port = port.split("\r\n").next().unwrap_or_default();
+                        let page = |name: &str| format!("<url><loc>http://127.0.0.1:{}/{}</loc></url>", port, name);
+                        let body = format!(
+                            r#"<?xml version="1.0" encoding="UTF-8"?><urlset xmlns="http://www.sitemaps.org/schemas/sitemap/0.9">{}{}{}</urlset>"#,
+                            page("first"),
+                            page("second"),
+                            page("third"),
+                        );
+                        http_response(200, &[], body.as_bytes())
+                    }
+                    "/first" => http_response(200, &[], b"discord.gg/UNWEj54 discord.gg/abcdef"),
+                    "/second" => http_response(200, &[], b"discord.gg/abcdef discord.gg/ghijkl"),
+                    _ => http_response(200, &[], b"discord.gg/zzzzzz"),
+                }
+            });
+            let sitemap_url = format!("{}/sitemap.xml", server.url);
+            assert_eq!(
+                resolve_sitemap(&sitemap_url).unwrap(),
+                vec![
+                    "https://discord.com/invite/UNWEj54",
+                    "https://discord.com/invite/abcdef",
+                    "https://discord.com/invite/ghijkl",
+                    "https://discord.com/invite/zzzzzz",
+                ]
+            );
+            assert_eq!(resolve_sitemap_with_limit(&sitemap_url, 1).unwrap().len(), 2);
+            assert_eq!(server.requests().len(), 6);
+        }
+
+        #[test]
+        fn resolve_lines() {
+            let server = MockServer::start(|request| {
+                if request.starts_with("GET /first ") {
+                    http_response(200, &[], b"discord.gg/UNWEj54")
+                } else {
+                    http_response(200, &[], b"discord.gg/8j8b2xR and discord.gg/Yyakf3")
+                }
+            });
+            let input = format!("{0}/first\n\n  {0}/second  \n", server.url);
+
+            let mut results = resolve_reader(Cursor::new(input));
+            assert_eq!(server.requests().len(), 0);
+            assert_eq!(
+                results.next().unwrap().unwrap(),
+                vec!["https://discord.com/invite/UNWEj54".to_string()]
+            );
+            assert_eq!(server.requests().len(), 1);
+            assert_eq!(
+                results.next().unwrap().unwrap(),
+                vec![
+                    "https://discord.com/invite/8j8b2xR".to_string(),
+                    "https://discord.com/invite/Yyakf3".to_string()
+                ]
+            );
+            assert!(results.next().is_none());
+        }
+
+        #[test]
+        fn stats_with_duplicates() {
+            let body = "discord.gg/UNWEj54 discord.gg/UNWEj54 discord.gg/a discord.gg/ discord.gg/8j8b2xR";
+            assert_eq!(
+                ResolveStats::from_body(body),
+                ResolveStats {
+                    invites: vec![
+                        "https://discord.com/invite/UNWEj54".to_string(),
+                        "https://discord.com/invite/8j8b2xR".to_string()
+                    ],
+                    total_hits: 5,
+                    unique: 2,
+                }
+            );
+        }
+
+        #[test]
+        fn skip_reasons() {
+            let server = MockServer::start(|_| {
+                let body = format!("discord.gg/a discord.gg/UNWEj54 discord.gg/a discord.gg/ab/cd discord.gg/{}", "x".repeat(101));
+                http_response(200, &[], body.as_bytes())
+            });
+            let (invites, skipped) = resolve_verbose(&server.url).unwrap();
+            assert_eq!(invites, vec!["https://discord.com/invite/UNWEj54".to_string()]);
+            assert_eq!(
+                skipped,
+                vec![
+                    SkipReason { raw: "a".to_string(), reason: Reason::TooShort },
+                    SkipReason { raw: "ab/cd".to_string(), reason: Reason::InvalidCharacters },
+                    SkipReason { raw: "x".repeat(101), reason: Reason::TooLong },
+                ]
+            );
+        }
+
+        #[test]
+        fn resolve_invalid_utf8() {
+            let server = MockServer::start(|_| {
+                http_response(200, &[], b"\xff\xfe discord.gg/UNWEj54 \xc3\x28 discord.gg/8j8b2xR\xff")
+            });
+            assert_eq!(
+                resolve(&server.url).unwrap(),
+                vec![
+                    "https://discord.com/invite/UNWEj54".to_string(),
+                    "https://discord.com/invite/8j8b2xR".to_string()
+                ]
+            );
+        }
+
+        #[test]
+        fn merge_stats() {
+            let first = ResolveStats::from_body("discord.gg/UNWEj54 discord.gg/8j8b2xR discord.gg/a");
+            let second = ResolveStats::from_body("discord.gg/8j8b2xR discord.gg/Yyakf3 discord.gg/Yyakf3");
+            let merged = first + second;
+            assert_eq!(
+                merged.invites,
+                vec![
+                    "https://discord.com/invite/UNWEj54".to_string(),
+                    "https://discord.com/invite/8j8b2xR".to_string(),
+                    "https://discord.com/invite/Yyakf3".to_string()
+                ]
+            );
+            assert_eq!(merged.total_hits, 6);
+            assert_eq!(merged.unique, 3);
+        }
+
+        #[test]
+        fn resolve_random_bytes() {
+            for input in crate::mock::random_inputs() {
+                let codes = extract_codes(&input);
+                assert!(codes.iter().all(|code| crate::discord::is_valid_code(code)));
+            }
+            assert!(extract_codes("application/ld+json</script> og:url discord.gg/").is_empty());
+
+            let server = MockServer::start(|request| {
+                let seed = request.len() as u64;
+                http_response(200, &[], &crate::mock::random_bytes(seed, 4096))
+            });
+            for i in 0..10 {
+                resolve(&format!("{}/{}", server.url, "a".repeat(i))).unwrap();
+            }
+        }
+
+        #[test]
+        fn extract_offsets() {
+            let body = r#"<html><head><meta property="og:url" content="https://discord.gg/UNWEj54"></head>
+<body><script type="application/ld+json">{"sameAs": "https://discord.com/invite/Yyakf3"}</script>
+<p>Join DISCORD.GG/8j8b2xR or discord.gg/UNWEj54, and discord.com/invite/abcdef- now</p></body></html>"#;
+            let offsets = extract_codes_with_offsets(body);
+            let codes: Vec<&str> = offsets.iter().map(|(code, _)| code.as_str()).collect();
+            assert_eq!(codes, vec!["UNWEj54", "Yyakf3", "8j8b2xR", "abcdef"]);
+            for (code, offset) in &offsets {
+                assert_eq!(&body[*offset..*offset + code.len()], code);
+            }
+            assert_eq!(offsets[0].1, body.find("UNWEj54").unwrap());
+            assert_eq!(offsets[2].1, body.find("8j8b2xR").unwrap());
+            assert_eq!(offsets[3].1, body.find("abcdef").unwrap());
+
+            let server = MockServer::start(|_| http_response(200, &[], b"abc discord.gg/UNWEj54"));
+            assert_eq!(resolve_with_offsets(&server.url).unwrap(), vec![("UNWEj54".to_string(), 15)]);
+        }
+
+        #[test]
+        fn extract_all_properties() {
+            for input in crate::mock::random_inputs() {
+                let codes = crate::extract_all(&input);
+                assert!(codes.iter().all(|code| crate::discord::is_valid_code(code)));
+                let unique: HashSet<&String> = codes.iter().collect();
+                assert_eq!(unique.len(), codes.len());
+                // every code comes from the input
+                assert!(codes.iter().all(|code| input.contains(code.as_str())));
+            }
+        }
+
+        #[test]
+        fn extract_markdown() {
+            let md = "Our server: [**join here**](https://discord.gg/UNWEj54 \"Discord\") and the [FR one][fr].\n\
+                      Text claiming [discord.gg/fakeinvite](https://discord.com/invite/8j8b2xR), \
+                      an autolink <https://discord.gg/Yyakf3> and a bare discord.gg/abc\\_def.\n\
+                      \n\
+                      [fr]: <https://discord.gg/frenchy> \"French\"\n\
+                      [unused]: https://example.com/\n";
+            assert_eq!(
+                extract_from_markdown(md),
+                vec!["UNWEj54", "8j8b2xR", "frenchy", "Yyakf3", "abc_def"]
+            );
+            assert_eq!(extract_from_markdown("[a](https://discord.gg/UNWEj54) discord.gg/UNWEj54"), vec!["UNWEj54"]);
+            assert_eq!(extract_from_markdown("[unclosed](https://discord.gg/UNWEj54"), vec!["UNWEj54"]);
+            for input in crate::mock::random_inputs() {
+                extract_from_markdown(&input);
+            }
+        }
+
+        #[test]
+        fn extract_from_text() {
+            let body = "discord.gg/UNWEj54 discord.gg/a discord.gg/UNWEj54 (discord.gg/8j8b2xR)";
+            assert_eq!(extract_codes(body), vec!["UNWEj54".to_string(), "8j8b2xR".to_string()]);
+
+            let body = format!("discord.gg/ab discord.gg/{} discord.gg/{}", "x".repeat(100), "y".repeat(101));
+            assert_eq!(extract_codes(&body), vec!["ab".to_string(), "x".repeat(100)]);
+        }
+
+        #[test]
+        fn extract_case_insensitive() {
+            let body = "DISCORD.GG/UNWEj54, Discord.Gg/8j8b2xR, https://DISCORD.com/Invite/Yyakf3, discord.company/abcdef";
+            assert_eq!(
+                extract_codes(body),
+                vec!["UNWEj54".to_string(), "8j8b2xR".to_string(), "Yyakf3".to_string()]
+            );
+        }
+
+        #[test]
+        fn extract_followed_by_punctuation() {
+            assert_eq!(extract_codes("join discord.gg/abc123."), vec!["abc123".to_string()]);
+            assert_eq!(extract_codes("(discord.gg/abc123)"), vec!["abc123".to_string()]);
+            assert_eq!(extract_codes("discord.gg/abc123, or"), vec!["abc123".to_string()]);
+            assert_eq!(extract_codes("join discord.gg/abc123- now"), vec!["abc123".to_string()]);
+            assert_eq!(extract_codes("join discord.gg/abc123_\n"), vec!["abc123".to_string()]);
+            assert_eq!(extract_codes("join discord.gg/abc123-"), vec!["abc123".to_string()]);
+            // only a single separator is trimmed, and only before whitespace
+            assert_eq!(extract_codes("discord.gg/abc123-- now"), vec!["abc123-".to_string()]);
+            assert_eq!(extract_codes("discord.gg/abc-123 now"), vec!["abc-123".to_string()]);
+            assert_eq!(extract_codes("discord.gg/abc123-."), vec!["abc123-".to_string()]);
+        }
+
+        #[test]
+        fn extract_repeated_invite() {
+            let body = "<a href=\"https://discord.gg/UNWEj54\">join</a>\n".repeat(1000);
+            let start = std::time::Instant::now();
+            assert_eq!(extract_codes(&body), vec!["UNWEj54".to_string()]);
+            assert!(start.elapsed() < std::time::Duration::from_secs(1));
+        }
+
+        #[test]
+        fn extract_from_og_url() {
+            let body = r#"<html><head>
+                <meta property="og:title" content="Our community">
+                <meta property="og:url" content="https://discord.com/invite/UNWEj54?utm_source=web">
+            </head><body>Click the button to join!</body></html>"#;
+            assert_eq!(extract_codes(body), vec!["UNWEj54".to_string()]);
+        }
+
+        #[test]
+        fn extract_from_json_ld() {
+            let body = r#"<script type="application/ld+json">
+                {"@type": "Organization", "sameAs": ["https://twitter.com/x", "https://discord.com/invite/8j8b2xR"]}
+            </script>"#;
+            assert_eq!(extract_codes(body), vec!["8j8b2xR".to_string()]);
+        }
+    }
+}
+
+/// Contains discord fetcher
+pub mod discord {
+    use super::client::{Client, DEFAULT_API_BASE};
+    use super::util::{host, is_in_domain};
+    use super::Error;
+    use log::debug;
+    use serde_json::Value;
+
+    use serde::{Deserialize, Serialize};
+    use std::cmp::Reverse;
+    use std::fmt;
+    use std::io::{self, Write};
+    use std::ops::RangeInclusive;
+    use std::time::{Duration, SystemTime, UNIX_EPOCH};
+    use string_tools::get_all_between_strict;
+
+    /// The first second of 2015, in milliseconds since the Unix epoch, from which snowflake timestamps are counted.
+    const DISCORD_EPOCH_MS: u64 = 1_420_070_400_000;
+
+    /// The lengths accepted by Discord for an invite code.  
+    /// Random codes are usually 7 to 10 characters long and vanity codes rarely exceed 25.
+    pub const INVITE_CODE_LENGTH: RangeInclusive<usize> = 2..=100;
+
+    /// Check that a string looks like an invite code: an allowed length and only alphanumeric characters, `-` or `_`.
+    pub fn is_valid_code(code: &str) -> bool {
+        INVITE_CODE_LENGTH.contains(&code.len())
+            && code.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    }
+
+    /// Produce the url of the Discord API returning the invite `code`, to make custom requests.
+    /// [`Invite::fetch`] requests `api_invite_url(code, true, false)`.  
+    /// `with_counts` adds the approximate member counts to the response, and `with_expiration` its expiration date.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use discord_finder::discord::api_invite_url;
+    ///
+    /// assert_eq!(api_invite_url("UNWEj54", true, false), "https://discord.com/api/v6/invites/UNWEj54?with_counts=true");
+    /// ```
+    pub fn api_invite_url(code: &str, with_counts: bool, with_expiration: bool) -> String {
+        invite_url(DEFAULT_API_BASE, code, with_counts, with_expiration)
+    }
+
+    pub(crate) fn invite_url(api_base: &str, code: &str, with_counts: bool, with_expiration: bool) -> String {
+        let mut url = format!("{}/v6/invites/{}", api_base, code);
+        let flags: Vec<&str> = [(with_counts, "with_counts=true"), (with_expiration, "with_expiration=true")]
+            .iter()
+            .filter(|(enabled, _)| *enabled)
+            .map(|(_, flag)| *flag)
+            .collect();
+        if !flags.is_empty() {
+            url.push('?');
+            url.push_str(&flags.join("&"));
+        }
+        url
+    }
+
+    /// The short link hosts of Discord, which redirect to invites among other pages.
+    const SHORT_LINK_HOSTS: [&str; 1] = ["dis.gd"];
+
+    /// Return true if `url` is a short link of Discord (for example `https://dis.gd/rust`), which has to be
+    /// followed to know the invite it leads to (see [`resolve_short_link`]).
+    pub fn is_short_link(url: &str) -> bool {
+        let host = host(url);
+        SHORT_LINK_HOSTS.iter().any(|short| is_in_domain(&host, short))
+    }
+
+    /// Follow the redirects of a Discord short link and return the url of the invite it leads to.  
+    /// Returns `Ok(None)` if `url` is not a short link or if it does not end on an invite
+    /// (marketing pages, or a redirect outside of Discord).
+    pub fn resolve_short_link(url: &str) -> Result<Option<String>, Error> {
+        Client::new().resolve_short_link(url)
+    }
+
+    /// Return true for the invisible characters that sneak into codes copied from HTML: zero-width spaces,
+    /// joiners, word joiners, byte order marks and soft hyphens.
+    pub(crate) fn is_zero_width(c: char) -> bool {
+        matches!(c, '\u{200B}'..='\u{200D}' | '\u{2060}' | '\u{FEFF}' | '\u{00AD}')
+    }
+
+    /// Remove the whitespace and the zero-width characters surrounding a code. The case is kept, since
+    /// codes are case-sensitive.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use discord_finder::discord::normalize_code;
+    ///
+    /// assert_eq!(normalize_code("\u{200B}UNWEj54\u{FEFF} "), "UNWEj54");
+    /// ```
+    pub fn normalize_code(code: &str) -> &str {
+        code.trim_matches(|c: char| c.is_whitespace() || is_zero_width(c))
+    }
+
+    /// Extract the id of the invitation from an url.  
+    /// The query string and the fragment are ignored.
+    pub fn get_invite_code(url: &str) -> Option<&str> {
+        let code = url
+            .strip_prefix("https://discord.com/invite/")
+            .or_else(|| url.strip_prefix("https://discord.gg/"))?;
+        let code = normalize_code(code.split(['?', '#']).next().unwrap_or_default());
+        if is_valid_code(code) {
+            Some(code)
+        } else {
+            None
+        }
+    }
+
+    /// An invite code checked with [`is_valid_code`].
+    #[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+    pub struct InviteCode(String);
+
+    impl InviteCode {
+        /// Return `None` if `code` is not a valid invite code.
+        pub fn new(code: &str) -> Option<InviteCode> {
+            if is_valid_code(code) {
+                Some(InviteCode(code.to_string()))
+            } else {
+                None
+            }
+        }
+
+        pub fn as_str(&self) -> &str {
+            &self.0
+        }
+
+        /// Return the url of the invite.
+        pub fn url(&self) -> String {
+            format!("https://discord.com/invite/{}", self.0)
+        }
+    }
+
+    impl AsRef<str> for InviteCode {
+        fn as_ref(&self) -> &str {
+            &self.0
+        }
+    }
+
+    impl PartialEq<&str> for InviteCode {
+        fn eq(&self, other: &&str) -> bool {
+            self.0 == *other
+        }
+    }
+
+    impl fmt::Display for InviteCode {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.write_str(&self.0)
+        }
+    }
+
+    impl From<InviteCode> for String {
+        fn from(code: InviteCode) -> String {
+            code.0
+        }
+    }
+
+    /// Extract the invite codes of a list pasted by a user: invite urls or bare codes separated by commas,
+    /// spaces or newlines.
+    /// The scheme and `www.` of the urls are optional. Invalid tokens are ignored, and each code is only
+    /// returned once, in order of appearance.  
+    /// Bare codes are only accepted on the lines holding nothing but a list, one code or url between each
+    /// comma. Other lines are considered as text, such as `Join discord.gg/UNWEj54 and play`, and only their
+    /// urls are kept.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use discord_finder::discord::parse_invite_list;
+    ///
+    /// let codes = parse_invite_list("discord.gg/UNWEj54, 8j8b2xR\nhttps://discord.com/invite/UNWEj54\njoin and play");
+    /// assert_eq!(codes, vec!["UNWEj54", "8j8b2xR"]);
+    /// ```
+    pub fn parse_invite_list(input: &str) -> Vec<InviteCode> {
+        let mut codes: Vec<InviteCode> = Vec::new();
+        for line in input.lines() {
+            let is_list = line.split(',').all(|entry| entry.split_whitespace().count() <= 1);
+            for token in line.split(|c: char| c == ',' || c.is_whitespace()) {
+                let host = token
+                    .strip_prefix("https://")
+                    .or_else(|| token.strip_prefix("http://"))
+                    .unwrap_or(token);
+                let host = host.strip_prefix("www.").unwrap_or(host);
+                let code = if host.contains('/') {
+                    get_invite_code(&format!("https://{}", host)).and_then(InviteCode::new)
+                } else if is_list {
+                    InviteCode::new(token)
+                } else {
+                    None
+                };
+                if let Some(code) = code {
+                    if !codes.contains(&code) {
+                        codes.push(code);
+                    }
+                }
+            }
+        }
+        codes
+    }
+
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct Guild {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        banner: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        description: Option<String>,
+        id: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        icon: Option<String>,
+        name: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        preferred_locale: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        splash: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        vanity_url_code: Option<String>,
+        verification_level: u8,
+    }
+
+    impl Guild {
+        pub fn id(&self) -> &str {
+            &self.id
+        }
+
+        pub fn name(&self) -> &str {
+            &self.name
+        }
+
+        /// Return the locale of the guild (for example "en-US"), if Discord provided it.
+        pub fn preferred_locale(&self) -> Option<&str> {
+            self.preferred_locale.as_deref()
+        }
+
+        /// Return the vanity url of the guild (`https://discord.gg/{vanity_url_code}`), if it has one.  
+        /// Unlike random invites, this link is stable.
+        pub fn vanity_url(&self) -> Option<String> {
+            self.vanity_url_code
+                .as_ref()
+                .map(|code| format!("https://discord.gg/{}", code))
+        }
+
+        /// Return the creation time of the guild, which is encoded in its id (a snowflake).  
+        /// The oldest guilds are usually the most legitimate ones. An invalid id gives the Discord epoch.
+        pub fn created_at(&self) -> SystemTime {
+            let snowflake: u64 = self.id.parse().unwrap_or_default();
+            UNIX_EPOCH + Duration::from_millis((snowflake >> 22) + DISCORD_EPOCH_MS)
+        }
+    }
+
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct Channel {
+        id: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        name: Option<String>,
+        r#type: usize,
+    }
+
+    impl Channel {
+        pub fn id(&self) -> &str {
+            &self.id
+        }
+
+        /// Return the url opening this channel in Discord, for a member of the guild `guild_id`.
+        pub fn jump_url(&self, guild_id: &str) -> String {
+            format!("https://discord.com/channels/{}/{}", guild_id, self.id)
+        }
+    }
+
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct User {
+        id: String,
+        username: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        avatar: Option<String>,
+        discriminator: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        public_flags: Option<u32>,
+    }
+
+    /// A public badge of a [`User`], decoded from its `public_flags`.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    pub enum Badge {
+        Staff,
+        Partner,
+        HypeSquadEvents,
+        BugHunterLevel1,
+        HypeSquadBravery,
+        HypeSquadBrilliance,
+        HypeSquadBalance,
+        EarlySupporter,
+        TeamUser,
+        BugHunterLevel2,
+        VerifiedBot,
+        EarlyVerifiedBotDeveloper,
+        CertifiedModerator,
+        BotHttpInteractions,
+        ActiveDeveloper,
+    }
+
+    impl Badge {
+        /// Every badge with its bit in `public_flags`.
+        const BITS: [(u32, Badge); 15] = [
+            (0, Badge::Staff),
+            (1, Badge::Partner),
+            (2, Badge::HypeSquadEvents),
+            (3, Badge::BugHunterLevel1),
+            (6, Badge::HypeSquadBravery),
+            (7, Badge::HypeSquadBrilliance),
+            (8, Badge::HypeSquadBalance),
+            (9, Badge::EarlySupporter),
+            (10, Badge::TeamUser),
+            (14, Badge::BugHunterLevel2),
+            (16, Badge::VerifiedBot),
+            (17, Badge::EarlyVerifiedBotDeveloper),
+            (18, Badge::CertifiedModerator),
+            (19, Badge::BotHttpInteractions),
+            (22, Badge::ActiveDeveloper),
+        ];
+    }
+
+    impl User {
+        /// Return the public badges of the user as a bitfield, if Discord provided them. See [`User::badges`].
+        pub fn public_flags(&self) -> Option<u32> {
+            self.public_flags
+        }
+
+        /// Decode the public badges of the user, in the order of their bits. Unknown bits are ignored,
+        /// and a user without `public_flags` has no badges.
+        pub fn badges(&self) -> Vec<Badge> {
+            let flags = self.public_flags.unwrap_or_default();
+            Badge::BITS
+                .iter()
+                .filter(|(bit, _)| flags & (1 << bit) != 0)
+                .map(|(_, badge)| *badge)
+                .collect()
+        }
+    }
+
+    #[cfg(any(test, not(feature = "simd-json")))]
+    fn parse_json_serde(body: &str) -> Result<Value, String> {
+        serde_json::from_str(body).map_err(|e| e.to_string())
+    }
+
+    #[cfg(feature = "simd-json")]
+    fn parse_json_simd(body: &str) -> Result<Value, String> {
+        // simd-json parses in place
+        let mut bytes = body.as_bytes().to_vec();
+        simd_json::serde::from_slice(&mut bytes).map_err(|e| e.to_string())
+    }
+
+    /// Parse a JSON payload with simd-json if the "simd-json" feature is enabled, and with serde_json otherwise.
+    fn parse_json(body: &str) -> Result<Value, String> {
+        #[cfg(feature = "simd-json")]
+        return parse_json_simd(body);
+        #[cfg(not(feature = "simd-json"))]
+        return parse_json_serde(body);
+    }
+
+    pub(crate) fn parse_raw(body: &str) -> Result<(Invite, Value), Error> {
+        let parsed = parse_json(body)
+            .and_then(|raw| Ok((Invite::deserialize(&raw).map_err(|e| e.to_string())?, raw)));
+        match parsed {
+            Ok(parsed) => Ok(parsed),
+            Err(e) => {
+                debug!("invite parsing error: {}", e);
+                Err(Error::ParseError)
+            }
+        }
+    }
+
+    /// Extract the code of a server template from an url (`https://discord.new/{code}` or
+    /// `https://discord.com/template/{code}`).  
+    /// Templates are not invites: they create a new server from a copy of the settings of another one.
+    pub fn get_template_code(url: &str) -> Option<&str> {
+        let code = url
+            .strip_prefix("https://discord.new/")
+            .or_else(|| url.strip_prefix("https://discord.com/template/"))?;
+        let code = code.split(['?', '#']).next().unwrap_or_default();
+        if is_valid_code(code) {
+            Some(code)
+        } else {
+            None
+        }
+    }
+
+    /// A server template, as returned by [`fetch_template`].
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct Template {
+        pub code: String,
+        pub name: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub description: Option<String>,
+        pub source_guild_id: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub usage_count: Option<u64>,
+    }
+
+    /// Loads a server template from its code.
+    pub fn fetch_template(code: &str) -> Result<Template, Error> {
+        Client::new().fetch_template(code)
+    }
+
+    /// A channel listed by a guild [`Widget`]: only the voice channels anyone can join are listed.
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct WidgetChannel {
+        pub id: String,
+        pub name: String,
+        pub position: i64,
+    }
+
+    /// An online member listed by a guild [`Widget`]. Ids are anonymized by Discord.
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct WidgetMember {
+        pub id: String,
+        pub username: String,
+        pub status: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub avatar_url: Option<String>,
+    }
+
+    /// The public widget of a guild, as returned by [`fetch_widget`].
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct Widget {
+        pub id: String,
+        pub name: String,
+        /// An invite created by the widget, if the guild configured one.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub instant_invite: Option<String>,
+        #[serde(default)]
+        pub channels: Vec<WidgetChannel>,
+        #[serde(default)]
+        pub members: Vec<WidgetMember>,
+        pub presence_count: u64,
+    }
+
+    /// Loads the widget of a guild from its id, which gives the online members and voice channels without any invite.  
+    /// Fails with [`Error::WidgetDisabled`] if the guild did not enable its widget.
+    pub fn fetch_widget(guild_id: &str) -> Result<Widget, Error> {
+        Client::new().fetch_widget(guild_id)
+    }
+
+    /// The preview of an invite declared by the OpenGraph tags of its HTML page, see [`Invite::fetch_og`].
+    #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+    pub struct OgPreview {
+        /// The name of the server.
+        pub title: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub description: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub image: Option<String>,
+    }
+
+    impl OgPreview {
+        /// Return the number of members mentioned by the description, as written: 1234 for "hang out with
+        /// 1,234 other members". Discord excludes the member reading it, so the figure can be one less than
+        /// the `approximate_member_count` of the invite.
+        pub fn member_count(&self) -> Option<u64> {
+            let description = self.description.as_deref()?;
+            let count = description.split(" other member").next()?.rsplit(' ').next()?;
+            let count: String = count.chars().filter(|c| *c != ',').collect();
+            count.parse::<u64>().ok()
+        }
+    }
+
+    /// Parse the OpenGraph tags of an invite page. Returns `None` without `og:title`, which is the case
+    /// of the pages of expired invites.
+    pub fn parse_og(html: &str) -> Option<OgPreview> {
+        Some(OgPreview {
+            title: og_content(html, "og:title")?,
+            description: og_content(html, "og:description"),
+            image: og_content(html, "og:image"),
+        })
+    }
+
+    /// Return the decoded `content` of the first meta tag declaring `property`.
+    fn og_content(html: &str, property: &str) -> Option<String> {
+        let marker = format!("property=\"{}\"", property);
+        let idx = html.find(&marker)?;
+        let tag_start = html[..idx].rfind('<')?;
+        let tag_end = html[idx..].find('>').map(|i| idx + i)?;
+        let content = get_all_between_strict(&html[tag_start..tag_end], "content=\"", "\"")?;
+        Some(decode_entities(content)).filter(|content| !content.is_empty())
+    }
+
+    /// Decode the HTML entities Discord uses in attribute values.
+    fn decode_entities(text: &str) -> String {
+        text.replace("&quot;", "\"")
+            .replace("&#x27;", "'")
+            .replace("&#39;", "'")
+            .replace("&lt;", "<")
+            .replace("&gt;", ">")
+            .replace("&amp;", "&")
+    }
+
+    /// The widths of the columns of [`Invite::to_table_row`], in characters.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct ColumnWidths {
+        pub name: usize,
+        pub members: usize,
+        pub online: usize,
+    }
+
+    impl Default for ColumnWidths {
+        fn default() -> ColumnWidths {
+            ColumnWidths {
+                name: 32,
+                members: 8,
+                online: 8,
+            }
+        }
+    }
+
+    /// Truncate `text` to `width` characters, ending with an ellipsis when it is cut.
+    fn truncate(text: &str, width: usize) -> String {
+        if text.chars().count() <= width {
+            return text.to_string();
+        }
+        let mut truncated: String = text.chars().take(width.saturating_sub(1)).collect();
+        if width > 0 {
+            truncated.push('…');
+        }
+        truncated
+    }
+
+    /// A simple struct used to store informations about a discord server invite link.
+    /// Can be serialized by activing the feature "serde-support"
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct Invite {
+        pub code: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub guild: Option<Guild>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub channel: Option<Channel>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub inviter: Option<User>,
+        /// Absent when the invite was fetched without `with_counts`.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub approximate_member_count: Option<u64>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub approximate_presence_count: Option<u64>,
+        /// The creation time of the invite, as an RFC 3339 timestamp. See [`Invite::created_datetime`].
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub created_at: Option<String>,
+    }
+
+    impl Invite {
+        /// Loads a discord.gg page and produces an Invite struct.
+        pub fn fetch(url: &str) -> Result<Invite, Error> {
+            Invite::fetch_raw(url).map(|(invite, _raw)| invite)
+        }
+
+        /// Same as [`Invite::fetch`] but also returns the raw JSON payload sent by Discord,
+        /// giving access to the fields this crate does not model.
+        pub fn fetch_raw(url: &str) -> Result<(Invite, Value), Error> {
+            Client::new().fetch_invite_raw(url)
+        }
+
+        /// Loads the HTML page of an invite and parses its OpenGraph preview (see [`Client::fetch_og`]).  
+        /// The name and member count of the server are available without using the rate-limited API.
+        pub fn fetch_og(url: &str) -> Result<OgPreview, Error> {
+            Client::new().fetch_og(url)
+        }
+
+        /// Return the channel the invite leads to, if Discord provided it.
+        pub fn channel(&self) -> Option<&Channel> {
+            self.channel.as_ref()
+        }
+
+        /// Return true if the invite leads to the guild `guild_id`.  
+        /// Useful to detect a vanity code taken over by another server. An invite without guild never matches.
+        pub fn points_to_guild(&self, guild_id: &str) -> bool {
+            self.guild.as_ref().is_some_and(|guild| guild.id == guild_id)
+        }
+
+        /// Return true if the code is the vanity code of the guild, a human-readable and stable link.  
+        /// Without guild data, the code is guessed from its shape: random codes are at most 10 alphanumeric
+        /// characters without `-`, and a shorter code is only considered a vanity code if it is made of lowercase
+        /// letters, as Discord stores them. Short vanity codes containing digits are thus missed.
+        pub fn is_vanity_code(&self) -> bool {
+            match &self.guild {
+                Some(guild) => guild.vanity_url_code.as_deref() == Some(self.code.as_str()),
+                None => {
+                    self.code.len() > 10
+                        || self.code.contains('-')
+                        || self.code.chars().all(|c| c.is_ascii_lowercase())
+                }
+            }
+        }
+
+        /// Return the url opening the channel the invite leads to, if Discord provided the guild and the channel.
+        pub fn channel_url(&self) -> Option<String> {
+            Some(self.channel.as_ref()?.jump_url(&self.guild.as_ref()?.id))
+        }
+
+        /// Return the id of the guild the invite leads to, the natural stable key of a server.  
+        /// Invites to group DMs have no guild and no key.
+        pub fn guild_key(&self) -> Option<String> {
+            self.guild.as_ref().map(|guild| guild.id.clone())
+        }
+
+        /// Return the key used to deduplicate invites: the guild id, so that every invite of a server is
+        /// considered the same, or the code for the invites without guild.
+        pub fn dedup_key(&self) -> String {
+            self.guild_key().unwrap_or_else(|| self.code.clone())
+        }
+
+        /// Return the creation time of the invite, if Discord provided a valid one.
+        pub fn created_datetime(&self) -> Option<SystemTime> {
+            self.created_at.as_deref().and_then(parse_rfc3339)
+        }
+
+        /// Return the url
+        pub fn get_url(&self) -> String {
+            format!("https://discord.com/invite/{}", self.code)
+        }
+
+        /// Return the approximate number of members, or `default` if it is unknown.
+        pub fn member_count_or(&self, default: u64) -> u64 {
+            self.approximate_member_count.unwrap_or(default)
+        }
+
+        /// Return the approximate number of online members, or `default` if it is unknown.
+        pub fn presence_count_or(&self, default: u64) -> u64 {
+            self.approximate_presence_count.unwrap_or(default)
+        }
+
+        /// Return a row of aligned columns for tabular display: the server name (truncated with an ellipsis
+        /// if too long), the member counts aligned to the right and the code. Unknown values are shown as `-`.
+        ///
+        /// # Examples
+        ///
+        /// ```
+        /// use discord_finder::discord::{ColumnWidths, Invite};
+        ///
+        /// let invite: Invite = serde_json::from_str(r#"{"code": "UNWEj54", "approximate_member_count": 1234}"#).unwrap();
+        /// assert_eq!(invite.to_table_row(&ColumnWidths { name: 6, members: 6, online: 4 }), "-         1234     -  UNWEj54");
+        /// ```
+        pub fn to_table_row(&self, widths: &ColumnWidths) -> String {
+            let name = self.guild.as_ref().map_or("-", |guild| guild.name.as_str());
+            let count = |count: Option<u64>| count.map_or_else(|| "-".to_string(), |count| count.to_string());
+            format!(
+                "{:<name_width$}  {:>members_width$}  {:>online_width$}  {}",
+                truncate(name, widths.name),
+                count(self.approximate_member_count),
+                count(self.approximate_presence_count),
+                self.code,
+                name_width = widths.name,
+                members_width = widths.members,
+                online_width = widths.online,
+            )
+        }
+
+        /// Return the known counts as `1234 members (567 online)`, leaving out the ones Discord did not provide.
+        pub(crate) fn counts_summary(&self) -> Option<String> {
+            match (self.approximate_member_count, self.approximate_presence_count) {
+                (Some(members), Some(online)) => Some(format!("{} members ({} online)", members, online)),
+                (Some(members), None) => Some(format!("{} members", members)),
+                (None, Some(online)) => Some(format!("{} online", online)),
+                (None, None) => None,
+            }
+        }
+
+        /// Return a one-line Markdown summary, ready to be posted in a chat.  
+        /// The server name is omitted when the invite has no guild and the counts when they are unknown.
+        pub fn to_markdown(&self) -> String {
+            let mut summary = String::new();
+            if let Some(guild) = &self.guild {
+                summary.push_str(&format!("**{}** — ", escape_markdown(&guild.name)));
+            }
+            if let Some(counts) = self.counts_summary() {
+                summary.push_str(&format!("{} — ", counts));
+            }
+            summary.push_str(&format!("<{}>", self.get_url()));
+            summary
+        }
+    }
+
+    /// Print a readable summary such as `Server Name (code) — 1234 members`. The name is omitted for the
+    /// invites without guild and the member count when Discord did not provide it.
+    impl fmt::Display for Invite {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            match &self.guild {
+                Some(guild) => write!(f, "{} ({})", guild.name, self.code)?,
+                None => write!(f, "{}", self.code)?,
+            }
+            if let Some(members) = self.approximate_member_count {
+                write!(f, " — {} members", members)?;
+            }
+            Ok(())
+        }
+    }
+
+    /// Parse an RFC 3339 timestamp (for example `2021-03-05T14:02:11.123000+00:00`).  
+    /// Times before the Unix epoch are not supported.
+    fn parse_rfc3339(timestamp: &str) -> Option<SystemTime> {
+        fn number(text: &str) -> Option<i64> {
+            if text.is_empty() || !text.bytes().all(|b| b.is_ascii_digit()) {
+                return None;
+            }
+            text.parse().ok()
+        }
+
+        let (date, time) = timestamp.split_once(['T', 't', ' '])?;
+        let mut date = date.splitn(3, '-');
+        let (year, month, day) = (number(date.next()?)?, number(date.next()?)?, number(date.next()?)?);
+
+        let offset_start = time.find(['Z', 'z', '+', '-'])?;
+        let (time, offset) = time.split_at(offset_start);
+        let offset = match offset {
+            "Z" | "z" => 0,
+            _ => {
+                let (hours, minutes) = offset[1..].split_once(':')?;
+                let (hours, minutes) = (number(hours)?, number(minutes)?);
+                if hours > 23 || minutes > 59 {
+                    return None;
+                }
+                let seconds = hours * 3600 + minutes * 60;
+                if offset.starts_with('-') {
+                    -seconds
+                } else {
+                    seconds
+                }
+            }
+        };
+        let (time, fraction) = time.split_once('.').unwrap_or((time, ""));
+        let mut time = time.splitn(3, ':');
+        let (hour, minute, second) = (number(time.next()?)?, number(time.next()?)?, number(time.next()?)?);
+        let nanos = if fraction.is_empty() {
+            0
+        } else {
+            // checked before slicing, which would panic inside a multi-byte character
+            if !fraction.bytes().all(|b| b.is_ascii_digit()) {
+                return None;
+            }
+            let digits = &fraction[..fraction.len().min(9)];
+            number(digits)? * 10i64.pow(9 - digits.len() as u32)
+        };
+        if year > 9999 || !(1..=12).contains(&month) || !(1..=31).contains(&day) || hour > 23 || minute > 59 || second > 60 {
+            return None;
+        }
+
+        // days since the epoch of a proleptic gregorian date (Howard Hinnant's algorithm)
+        let year = if month <= 2 { year - 1 } else { year };
+        let era = year.div_euclid(400);
+        let year_of_era = year - era * 400;
+        let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+        let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+        let days = era * 146_097 + day_of_era - 719_468;
+
+        let seconds = days * 86400 + hour * 3600 + minute * 60 + second - offset;
+        if seconds < 0 {
+            return None;
+        }
+        Some(UNIX_EPOCH + Duration::new(seconds as u64, nanos as u32))
+    }
+
+    fn escape_markdown(text: &str) -> String {
+        let mut escaped = String::with_capacity(text.len());
+        for c in text.chars() {
+            if matches!(c, '\\' | '*' | '_' | '~' | '`' | '|') {
+                escaped.push('\\');
+            }
+            escaped.push(c);
+        }
+        escaped
+    }
+
+    fn escape_csv(field: &str) -> String {
+        if field.contains([',', '"', '\n', '\r']) {
+            format!("\"{}\"", field.replace('"', "\"\""))
+        } else {
+            field.to_string()
+        }
+    }
+
+    /// Sort invites from the most active server to the least active one: by online members,
+    /// then by members. Unknown counts come last, and invites with the same counts keep their order.
+    pub fn sort_by_activity(invites: &mut [Invite]) {
+        invites.sort_by_key(|invite| Reverse((invite.approximate_presence_count, invite.approximate_member_count)));
+    }
+
+    /// Write the url of each invite (see [`Invite::get_url`]), one per line, to pipe them to another program.
+    pub fn write_invite_urls<W: Write>(invites: &[Invite], w: &mut W) -> io::Result<()> {
+        for invite in invites {
+            writeln!(w, "{}", invite.get_url())?;
+        }
+        Ok(())
+    }
+
+    /// Produce a CSV document with a header row and one row per invite.  
+    /// Columns are the code, the server name, the member count, the online member count and the inviter (`username#discriminator`).
+    pub fn invites_to_csv(invites: &[Invite]) -> String {
+        let mut csv = String::from("code,name,members,online,inviter\n");
+        for invite in invites {
+            let name = invite.guild.as_ref().map(|guild| guild.name.as_str()).unwrap_or_default();
+            let inviter = invite
+                .inviter
+                .as_ref()
+                .map(|user| format!("{}#{}", user.username, user.discriminator))
+                .unwrap_or_default();
+            csv.push_str(&format!(
+                "{},{},{},{},{}\n",
+                escape_csv(&invite.code),
+                escape_csv(name),
+                invite.approximate_member_count.map(|count| count.to_string()).unwrap_or_default(),
+                invite.approximate_presence_count.map(|count| count.to_string()).unwrap_or_default(),
+                escape_csv(&inviter)
+            ));
+        }
+        csv
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use serde_json::from_str;
+        use std::thread::sleep;
+        use std::time::Duration;
+
+        #[test]
+        fn test_invite_struct() {
+            let invite =
+                Invite::fetch("https://discord.com/invite/seaofthievescommunity")
+                    .unwrap();
+            println!("{:#?}", invite);
+
+            sleep(Duration::from_secs(5));
+
+            let invite = Invite::fetch("https://discord.com/invite/UNWEj54").unwrap();
+            println!("{:#?}", invite);
+
+            sleep(Duration::from_secs(5));
+
+            let invite =
+            Invite::fetch("https://discord.gg/Yyakf3").unwrap();
+            println!("{:#?}", invite);
+        }
+
+        #[test]
+        fn get_invite_urls() {
+            assert_eq!(
+                get_invite_code("https://discord.com/invite/seaofthievescommunity"),
+                Some("seaofthievescommunity")
+            );
+            assert_eq!(
+                get_invite_code("https://discord.com/invite/UNWEj54"),
+                Some("UNWEj54")
+            );
+            assert_eq!(
+                get_invite_code("https://discord.gg/8j8b2xR"),
+                Some("8j8b2xR")
+            );
+            assert_eq!(
+                get_invite_code("https://discord.gg/Yyakf3"),
+                Some("Yyakf3")
+            );
+            assert_eq!(
+                get_invite_code("https://discord.gg/Yyakf3?event=1#top"),
+                Some("Yyakf3")
+            );
+            assert_eq!(get_invite_code("https://discord.gg/a"), None);
+            assert_eq!(get_invite_code("https://discord.gg/abc.def"), None);
+        }
+
+        #[test]
+        fn invite_list() {
+            let input = "https://discord.gg/UNWEj54,8j8b2xR \n\n discord.com/invite/Yyakf3,,\t\
+                         http://www.discord.gg/UNWEj54?event=1\r\nnot/a/code, a, https://example.com/abcdef ,seaofthievescommunity\n8j8b2xR";
+            assert_eq!(
+                parse_invite_list(input),
+                vec!["UNWEj54", "8j8b2xR", "Yyakf3", "seaofthievescommunity"]
+            );
+            assert!(parse_invite_list(" ,\n").is_empty());
+
+            // prose is not a list of bare codes, but its urls are kept
+            assert!(parse_invite_list("join and play").is_empty());
+            assert_eq!(
+                parse_invite_list("Come chill with us: discord.gg/UNWEj54 and bring friends\nabcdef ghijkl, 8j8b2xR"),
+                vec!["UNWEj54"]
+            );
+            assert_eq!(parse_invite_list("UNWEj54")[0].url(), "https://discord.com/invite/UNWEj54");
+        }
+
+        #[test]
+        fn invite_code_length_bounds() {
+            assert!(!is_valid_code(""));
+            assert!(!is_valid_code("a"));
+            assert!(is_valid_code("ab"));
+            assert!(is_valid_code("UNWEj54"));
+            assert!(is_valid_code("seaofthievescommunity"));
+            assert!(is_valid_code(&"a".repeat(100)));
+            assert!(!is_valid_code(&"a".repeat(101)));
+            assert!(!is_valid_code("abc/def"));
+
+            let code = "c".repeat(100);
+            let url = format!("https://discord.gg/{}", code);
+            assert_eq!(get_invite_code(&url), Some(code.as_str()));
+            let url = format!("https://discord.gg/{}", "c".repeat(101));
+            assert_eq!(get_invite_code(&url), None);
+        }
+
+        #[test]
+        fn guild_preferred_locale() {
+            let guild: Guild = from_str(
+                r#"{"id": "1", "name": "Server", "preferred_locale": "fr", "verification_level": 0}"#,
+            )
+            .unwrap();
+            assert_eq!(guild.preferred_locale(), Some("fr"));
+
+            let guild: Guild =
+                from_str(r#"{"id": "1", "name": "Server", "verification_level": 0}"#).unwrap();
+            assert_eq!(guild.preferred_locale(), None);
+            assert!(!serde_json::to_string(&guild).unwrap().contains("preferred_locale"));
+        }
+
+        #[test]
+        fn guild_creation_time() {
+            let guild: Guild =
+                from_str(r#"{"id": "175928847299117063", "name": "Server", "verification_level": 0}"#).unwrap();
+            // 2016-04-30 11:18:25.796 UTC
+            assert_eq!(guild.created_at(), UNIX_EPOCH + Duration::from_millis(1_462_015_105_796));
+
+            let guild: Guild = from_str(r#"{"id": "invalid", "name": "Server", "verification_level": 0}"#).unwrap();
+            assert_eq!(guild.created_at(), UNIX_EPOCH + Duration::from_millis(DISCORD_EPOCH_MS));
+        }
+
+        #[test]
+        fn parse_raw_payload() {
+            let (invite, raw) = parse_raw(
+                r#"{
+                    "code": "UNWEj54",
+                    "channel": {"id": "2", "type": 0},
+                    "approximate_member_count": 10,
+                    "approximate_presence_count": 2,
+                    "expires_at": null
+                }"#,
+            )
+            .unwrap();
+            assert_eq!(invite.code, "UNWEj54");
+            assert_eq!(raw["code"], "UNWEj54");
+            assert!(raw.get("expires_at").is_some());
+            assert!(matches!(parse_raw("{}"), Err(Error::ParseError)));
+            assert!(matches!(parse_raw("<html>"), Err(Error::ParseError)));
+        }
+
+        #[cfg(feature = "simd-json")]
+        #[test]
+        fn json_backends_agree() {
+            let body = r#"{
+                "code": "UNWEj54",
+                "guild": {"id": "1", "name": "Sérveur \"test\" 🎮", "verification_level": 0, "features": ["NEWS"]},
+                "channel": {"id": "2", "name": "général", "type": 0},
+                "inviter": {"id": "3", "username": "someone", "avatar": null, "discriminator": "0001"},
+                "approximate_member_count": 12345678901,
+                "approximate_presence_count": 2
+            }"#;
+            let serde = parse_json_serde(body).unwrap();
+            let simd = parse_json_simd(body).unwrap();
+            assert_eq!(serde, simd);
+
+            let serde = Invite::deserialize(&serde).unwrap();
+            let simd = Invite::deserialize(&simd).unwrap();
+            assert_eq!(serde_json::to_value(&serde).unwrap(), serde_json::to_value(&simd).unwrap());
+            assert!(parse_json_simd("{").is_err());
+        }
+
+        #[test]
+        fn parse_codes_without_panicking() {
+            // the prefix used to be compared by slicing, which panicked inside multi-byte characters
+            assert_eq!(get_invite_code("https://discord.com/invit€abc"), None);
+            assert_eq!(get_invite_code("https://discord.g€abc"), None);
+            for input in crate::mock::random_inputs() {
+                if let Some(code) = get_invite_code(&input) {
+                    assert!(is_valid_code(code));
+                }
+                get_template_code(&input);
+            }
+        }
+
+        #[test]
+        fn invite_without_channel() {
+            let (invite, _raw) = parse_raw(
+                r#"{
+                    "code": "UNWEj54",
+                    "guild": {"id": "1", "name": "Server", "verification_level": 0},
+                    "approximate_member_count": 10,
+                    "approximate_presence_count": 2
+                }"#,
+            )
+            .unwrap();
+            assert!(invite.channel().is_none());
+            assert!(!serde_json::to_string(&invite).unwrap().contains("channel"));
+        }
+
+        #[test]
+        fn activity_order() {
+            let invite = |code: &str, members: u64, online: u64| -> Invite {
+                from_str(&format!(
+                    r#"{{"code": "{}", "approximate_member_count": {}, "approximate_presence_count": {}}}"#,
+                    code, members, online
+                ))
+                .unwrap()
+            };
+            let mut invites = vec![
+                invite("quiet", 1000, 5),
+                invite("busy", 50, 40),
+                invite("small", 10, 5),
+                invite("empty", 3, 0),
+                invite("big", 2000, 40),
+                invite("tie", 10, 5),
+            ];
+            sort_by_activity(&mut invites);
+            let codes: Vec<&str> = invites.iter().map(|invite| invite.code.as_str()).collect();
+            assert_eq!(codes, vec!["big", "busy", "quiet", "small", "tie", "empty"]);
+        }
+
+        #[test]
+        fn invite_creation_time() {
+            let invite: Invite = from_str(
+                r#"{"code": "UNWEj54", "created_at": "2021-03-05T14:02:11.123000+00:00", "expires_at": null}"#,
+            )
+            .unwrap();
+            assert_eq!(invite.created_at.as_deref(), Some("2021-03-05T14:02:11.123000+00:00"));
+            assert_eq!(invite.created_datetime(), Some(UNIX_EPOCH + Duration::from_millis(1_614_952_931_123)));
+
+            let invite: Invite = from_str(r#"{"code": "UNWEj54"}"#).unwrap();
+            assert_eq!(invite.created_datetime(), None);
+            assert!(!serde_json::to_string(&invite).unwrap().contains("created_at"));
+        }
+
+        #[test]
+        fn rfc3339_timestamps() {
+            assert_eq!(parse_rfc3339("1970-01-01T00:00:00Z"), Some(UNIX_EPOCH));
+            assert_eq!(
+                parse_rfc3339("2016-04-30T11:18:25.796Z"),
+                Some(UNIX_EPOCH + Duration::from_millis(1_462_015_105_796))
+            );
+            assert_eq!(
+                parse_rfc3339("2016-04-30T13:18:25.796+02:00"),
+                parse_rfc3339("2016-04-30T11:18:25.796Z")
+            );
+            assert_eq!(
+                parse_rfc3339("2016-04-30T06:18:25-05:00"),
+                Some(UNIX_EPOCH + Duration::from_secs(1_462_015_105))
+            );
+            assert_eq!(
+                parse_rfc3339("2024-02-29T00:00:00.000000001+00:00"),
+                Some(UNIX_EPOCH + Duration::new(1_709_164_800, 1))
+            );
+            assert_eq!(parse_rfc3339("1969-12-31T23:59:59Z"), None);
+            assert_eq!(parse_rfc3339("2021-13-01T00:00:00Z"), None);
+            assert_eq!(parse_rfc3339("99999999999999999-01-01T00:00:00+99999999999999:00"), None);
+            assert_eq!(parse_rfc3339("2021-03-05T14:02:11"), None);
+            assert_eq!(parse_rfc3339("2021-03-05"), None);
+            assert_eq!(parse_rfc3339("yesterday"), None);
+            assert_eq!(parse_rfc3339("2021-03-05T14:02:11.12345678é+00:00"), None);
+            assert_eq!(parse_rfc3339("2021-03-05T14:02:11.1234€+00:00"), None);
+            for input in crate::mock::random_inputs() {
+                parse_rfc3339(&input);
+            }
+        }
+
+        #[test]
+        fn channel_jump_url() {
+            let invite: Invite = from_str(
+                r#"{
+                    "code": "rust",
+                    "guild": {"id": "273534239310479360", "name": "Rust", "verification_level": 0},
+                    "channel": {"id": "273541522815713281", "name": "welcome", "type": 0}
+                }"#,
+            )
+            .unwrap();
+            let channel = invite.channel().unwrap();
+            assert_eq!(channel.id(), "273541522815713281");
+            assert_eq!(channel.jump_url("1"), "https://discord.com/channels/1/273541522815713281");
+            assert_eq!(
+                invite.channel_url().as_deref(),
+                Some("https://discord.com/channels/273534239310479360/273541522815713281")
+            );
+
+            let group_dm: Invite = from_str(r#"{"code": "Yyakf3", "channel": {"id": "2", "type": 3}}"#).unwrap();
+            assert_eq!(group_dm.channel_url(), None);
+        }
+
+        #[test]
+        fn guild_check() {
+            let invite: Invite = from_str(
+                r#"{"code": "rust", "guild": {"id": "273534239310479360", "name": "Rust", "verification_level": 0}}"#,
+            )
+            .unwrap();
+            assert!(invite.points_to_guild("273534239310479360"));
+            assert!(!invite.points_to_guild("81384788765712384"));
+            assert!(!invite.points_to_guild(""));
+
+            let invite: Invite = from_str(r#"{"code": "rust"}"#).unwrap();
+            assert!(!invite.points_to_guild("273534239310479360"));
+        }
+
+        #[test]
+        fn dedup_keys() {
+            let first: Invite = from_str(
+                r#"{"code": "rust", "guild": {"id": "273534239310479360", "name": "Rust", "verification_level": 0}}"#,
+            )
+            .unwrap();
+            let second: Invite = from_str(
+                r#"{"code": "8j8b2xR", "guild": {"id": "273534239310479360", "name": "Rust", "verification_level": 0}}"#,
+            )
+            .unwrap();
+            assert_eq!(first.guild_key().as_deref(), Some("273534239310479360"));
+            assert_eq!(first.dedup_key(), "273534239310479360");
+            assert_eq!(first.dedup_key(), second.dedup_key());
+
+            let group_dm: Invite = from_str(r#"{"code": "Yyakf3", "channel": {"id": "2", "type": 3}}"#).unwrap();
+            assert_eq!(group_dm.guild_key(), None);
+            assert_eq!(group_dm.dedup_key(), "Yyakf3");
+        }
+
+        #[test]
+        fn counts_with_default() {
+            let invite: Invite = from_str(
+                r#"{"code": "UNWEj54", "approximate_member_count": 10, "approximate_presence_count": 2}"#,
+            )
+            .unwrap();
+            assert_eq!(invite.member_count_or(0), 10);
+            assert_eq!(invite.presence_count_or(0), 2);
+
+            let mut invites = vec![from_str::<Invite>(r#"{"code": "Yyakf3"}"#).unwrap(), invite];
+            assert_eq!(invites[0].member_count_or(7), 7);
+            assert_eq!(invites[0].presence_count_or(7), 7);
+            assert!(!serde_json::to_string(&invites[0]).unwrap().contains("approximate"));
+            sort_by_activity(&mut invites);
+            assert_eq!(invites[1].code, "Yyakf3");
+        }
+
+        #[test]
+        fn url_lines() {
+            let invites: Vec<Invite> = from_str(r#"[{"code": "UNWEj54"}, {"code": "Yyakf3"}]"#).unwrap();
+            let mut buffer = Vec::new();
+            write_invite_urls(&invites, &mut buffer).unwrap();
+            assert_eq!(
+                String::from_utf8(buffer).unwrap(),
+                "https://discord.com/invite/UNWEj54\nhttps://discord.com/invite/Yyakf3\n"
+            );
+
+            let mut buffer = Vec::new();
+            write_invite_urls(&[], &mut buffer).unwrap();
+            assert!(buffer.is_empty());
+        }
+
+        #[test]
+        fn csv_export() {
+            let invites: Vec<Invite> = from_str(
+                r#"[{
+                    "code": "UNWEj54",
+                    "guild": {"id": "1", "name": "Rust, \"the\" language", "verification_level": 0},
+                    "channel": {"id": "2", "type": 0},
+                    "inviter": {"id": "3", "username": "ferris", "discriminator": "0001"},
+                    "approximate_member_count": 1234,
+                    "approximate_presence_count": 567
+                }, {
+                    "code": "Yyakf3",
+                    "channel": {"id": "2", "type": 3},
+                    "approximate_member_count": 3,
+                    "approximate_presence_count": 0
+                }]"#,
+            )
+            .unwrap();
+            assert_eq!(
+                invites_to_csv(&invites),
+                "code,name,members,online,inviter\n\
+                 UNWEj54,\"Rust, \"\"the\"\" language\",1234,567,ferris#0001\n\
+                 Yyakf3,,3,0,\n"
+            );
+        }
+
+        #[test]
+        fn guild_vanity_url() {
+            let guild: Guild = from_str(
+                r#"{"id": "1", "name": "Sea of Thieves", "vanity_url_code": "seaofthievescommunity", "verification_level": 0}"#,
+            )
+            .unwrap();
+            assert_eq!(
+                guild.vanity_url(),
+                Some("https://discord.gg/seaofthievescommunity".to_string())
+            );
+
+            let guild: Guild =
+                from_str(r#"{"id": "1", "name": "Server", "vanity_url_code": null, "verification_level": 0}"#).unwrap();
+            assert_eq!(guild.vanity_url(), None);
+        }
+
+        #[test]
+        fn display() {
+            let invite: Invite = from_str(
+                r#"{"code": "UNWEj54", "guild": {"id": "1", "name": "Rust", "verification_level": 0}, "approximate_member_count": 1234}"#,
+            )
+            .unwrap();
+            assert_eq!(invite.to_string(), "Rust (UNWEj54) — 1234 members");
+
+            let invite: Invite = from_str(r#"{"code": "8j8b2xR", "approximate_member_count": 3}"#).unwrap();
+            assert_eq!(format!("{}", invite), "8j8b2xR — 3 members");
+            let invite: Invite = from_str(r#"{"code": "8j8b2xR"}"#).unwrap();
+            assert_eq!(format!("{}", invite), "8j8b2xR");
+        }
+
+        #[test]
+        fn invite_api_urls() {
+            let base = "https://discord.com/api/v6/invites/UNWEj54";
+            assert_eq!(api_invite_url("UNWEj54", false, false), base);
+            assert_eq!(api_invite_url("UNWEj54", true, false), format!("{}?with_counts=true", base));
+            assert_eq!(api_invite_url("UNWEj54", false, true), format!("{}?with_expiration=true", base));
+            assert_eq!(
+                api_invite_url("UNWEj54", true, true),
+                format!("{}?with_counts=true&with_expiration=true", base)
+            );
+        }
+
+        #[test]
+        fn table_rows() {
+            let widths = ColumnWidths {
+                name: 10,
+                members: 6,
+                online: 5,
+            };
+            let invite: Invite = from_str(
+                r#"{"code": "UNWEj54", "guild": {"id": "1", "name": "Rust Programming Language", "verification_level": 0},
+                "approximate_member_count": 31276, "approximate_presence_count": 4127}"#,
+            )
+            .unwrap();
+            assert_eq!(invite.to_table_row(&widths), "Rust Prog…   31276   4127  UNWEj54");
+            let invite: Invite = from_str(
+                r#"{"code": "8j8b2xR", "guild": {"id": "2", "name": "Café", "verification_level": 0}, "approximate_member_count": 12}"#,
+            )
+            .unwrap();
+            assert_eq!(invite.to_table_row(&widths), "Café            12      -  8j8b2xR");
+            assert_eq!(truncate("Rust", 0), "");
+            assert_eq!(truncate("Rust", 1), "…");
+        }
+
+        #[test]
+        fn user_badges() {
+            // bits 1, 6, 8, 9, 17 and 22, and the unknown bit 20
+            let user: User =
+                from_str(r#"{"id": "3", "username": "ferris", "discriminator": "0001", "public_flags": 5374786}"#).unwrap();
+            assert_eq!(
+                user.badges(),
+                vec![
+                    Badge::Partner,
+                    Badge::HypeSquadBravery,
+                    Badge::HypeSquadBalance,
+                    Badge::EarlySupporter,
+                    Badge::EarlyVerifiedBotDeveloper,
+                    Badge::ActiveDeveloper,
+                ]
+            );
+            assert_eq!(user.public_flags(), Some(5374786));
+            let user: User = from_str(r#"{"id": "3", "username": "ferris", "discriminator": "0001"}"#).unwrap();
+            assert!(user.badges().is_empty());
+            assert_eq!(user.public_flags(), None);
+        }
+
+        #[test]
+        fn vanity_codes() {
+            let guild = r#"{"id": "1", "name": "Sea of Thieves", "vanity_url_code": "seaofthievescommunity", "verification_level": 0}"#;
+            let invite = |code: &str, guild: Option<&str>| -> Invite {
+                let guild = guild.map(|guild| format!(r#", "guild": {}"#, guild)).unwrap_or_default();
+                from_str(&format!(r#"{{"code": "{}"{}}}"#, code, guild)).unwrap()
+            };
+            assert!(invite("seaofthievescommunity", Some(guild)).is_vanity_code());
+            assert!(!invite("UNWEj54", Some(guild)).is_vanity_code());
+            assert!(!invite("rust", Some(r#"{"id": "1", "name": "Rust", "verification_level": 0}"#)).is_vanity_code());
+
+            // without guild data
+            assert!(invite("minecraft", None).is_vanity_code());
+            assert!(invite("rust-lang", None).is_vanity_code());
+            assert!(invite("TheCoolestServerEver", None).is_vanity_code());
+            assert!(!invite("UNWEj54", None).is_vanity_code());
+            assert!(!invite("8j8b2xRq9K", None).is_vanity_code());
+            assert!(!invite("8j8b2xrq", None).is_vanity_code());
+            assert!(!invite("12345678", None).is_vanity_code());
+            assert!(!invite("qHpZrkEr", None).is_vanity_code());
+        }
+
+        #[test]
+        fn og_preview() {
+            let page = r#"<!DOCTYPE html><html lang="en"><head><meta charSet="utf-8"/>
+                <title>Discord</title>
+                <meta property="og:type" content="website"/>
+                <meta property="og:site_name" content="Discord"/>
+                <meta property="og:title" content="Rust Programming Language Community Server"/>
+                <meta property="og:description" content="Check out the Rust Programming Language Community Server community on Discord - hang out with 31,276 other members and enjoy free voice and text chat."/>
+                <meta property="og:image" content="https://cdn.discordapp.com/icons/273534239310479360/a_4b2d.png?size=256"/>
+                <meta name="twitter:card" content="summary"/>
+                </head><body><div id="app-mount"></div></body></html>"#;
+            let preview = parse_og(page).unwrap();
+            assert_eq!(preview.title, "Rust Programming Language Community Server");
+            assert_eq!(preview.member_count(), Some(31_276));
+            assert_eq!(
+                preview.image.as_deref(),
+                Some("https://cdn.discordapp.com/icons/273534239310479360/a_4b2d.png?size=256")
+            );
+
+            let page = r#"<meta property="og:title" content="Tom &amp; Jerry&#x27;s"/><meta property="og:description" content="Discord is great"/>"#;
+            let preview = parse_og(page).unwrap();
+            assert_eq!(preview.title, "Tom & Jerry's");
+            assert_eq!(preview.member_count(), None);
+
+            // expired invites have no preview
+            assert!(parse_og(r#"<meta property="og:site_name" content="Discord"/>"#).is_none());
+            for input in crate::mock::random_inputs() {
+                let _ = parse_og(&input);
+            }
+        }
+
+        #[test]
+        fn template_codes() {
+            assert_eq!(get_template_code("https://discord.new/hgM48av5Q69A"), Some("hgM48av5Q69A"));
+            assert_eq!(get_template_code("https://discord.com/template/hgM48av5Q69A?ref=x"), Some("hgM48av5Q69A"));
+            assert_eq!(get_template_code("https://discord.gg/hgM48av5Q69A"), None);
+            assert_eq!(get_invite_code("https://discord.new/hgM48av5Q69A"), None);
+        }
+
+        #[test]
+        fn template_payload() {
+            let template: Template = from_str(
+                r#"{
+                    "code": "hgM48av5Q69A",
+                    "name": "Friends & Family",
+                    "description": "A server to chat with your friends.",
+                    "usage_count": 49605,
+                    "creator_id": "132837293881950208",
+                    "source_guild_id": "678070694164299796",
+                    "serialized_source_guild": {"name": "Friends & Family"}
+                }"#,
+            )
+            .unwrap();
+            assert_eq!(template.code, "hgM48av5Q69A");
+            assert_eq!(template.name, "Friends & Family");
+            assert_eq!(template.description.as_deref(), Some("A server to chat with your friends."));
+            assert_eq!(template.source_guild_id, "678070694164299796");
+            assert_eq!(template.usage_count, Some(49605));
+        }
+
+        #[test]
+        fn invite_to_markdown() {
+            let invite: Invite = from_str(
+                r#"{
+                    "code": "UNWEj54",
+                    "guild": {"id": "1", "name": "Sea_of_Thieves", "verification_level": 0},
+                    "channel": {"id": "2", "name": "general", "type": 0},
+                    "approximate_member_count": 1234,
+                    "approximate_presence_count": 567
+                }"#,
+            )
+            .unwrap();
+            assert_eq!(
+                invite.to_markdown(),
+                "**Sea\\_of\\_Thieves** — 1234 members (567 online) — <https://discord.com/invite/UNWEj54>"
+            );
+
+            let invite: Invite = from_str(
+                r#"{
+                    "code": "Yyakf3",
+                    "channel": {"id": "2", "type": 3},
+                    "approximate_member_count": 3,
+                    "approximate_presence_count": 0
+                }"#,
+            )
+            .unwrap();
+            assert_eq!(
+                invite.to_markdown(),
+                "3 members (0 online) — <https://discord.com/invite/Yyakf3>"
+            );
+
+            let invite: Invite = from_str(
+                r#"{"code": "8j8b2xR", "guild": {"id": "1", "name": "Cats", "verification_level": 0}}"#,
+            )
+            .unwrap();
+            assert_eq!(invite.to_markdown(), "**Cats** — <https://discord.com/invite/8j8b2xR>");
+        }
+    }
+}
+
+/// Contains the HTTP implementations a [`Client`](client::Client) can send its requests with.  
+/// [`MinreqBackend`](backend::MinreqBackend) is used by default. Applications already depending on reqwest can
+/// disable the default features and enable the "reqwest-backend" feature to use
+/// [`ReqwestBackend`](backend::ReqwestBackend) instead.
+pub mod backend {
+    use super::client::RawResponse;
+    use super::Error;
+    use log::debug;
+    use std::fmt::Debug;
+
+    #[cfg(not(any(feature = "minreq-backend", feature = "reqwest-backend")))]
+    compile_error!("enable the \"minreq-backend\" or the \"reqwest-backend\" feature");
+
+    /// A GET request prepared by a [`Client`](crate::client::Client).
+    #[derive(Debug, Clone)]
+    pub struct HttpRequest<'a> {
+        pub url: &'a str,
+        /// Every header to send, defaults included.
+        pub headers: Vec<(String, String)>,
+        /// Fail with [`Error::ResponseTooLarge`] if the body exceeds this size.
+        pub max_body_bytes: usize,
+        /// The HTTP proxy to send the request through. Only set with the "proxy" feature.
+        pub proxy: Option<String>,
+    }
+
+    /// An HTTP implementation. Header names of the responses must be lowercase and their url must be
+    /// the one of the last redirection.
+    pub trait HttpClient: Debug + Send + Sync {
+        fn get(&self, request: &HttpRequest) -> Result<RawResponse, Error>;
+    }
+
+    pub(crate) fn default_http_client() -> Box<dyn HttpClient> {
+        #[cfg(feature = "minreq-backend")]
+        return Box::new(MinreqBackend);
+        #[cfg(not(feature = "minreq-backend"))]
+        return Box::new(ReqwestBackend::new());
+    }
+
+    /// Sends the requests with minreq. Requires the "minreq-backend" feature, enabled by default.
+    #[cfg(feature = "minreq-backend")]
+    #[derive(Debug, Clone, Copy, Default)]
+    pub struct MinreqBackend;
+
+    #[cfg(feature = "minreq-backend")]
+    impl HttpClient for MinreqBackend {
+        fn get(&self, request: &HttpRequest) -> Result<RawResponse, Error> {
+            use std::io::Read;
+
+            let url = request.url;
+            let mut minreq_request = minreq::get(url);
+            for (name, value) in &request.headers {
+                minreq_request = minreq_request.with_header(name.as_str(), value.as_str());
+            }
+            #[cfg(feature = "proxy")]
+            if let Some(proxy) = &request.proxy {
+                let proxy = minreq::Proxy::new(proxy).map_err(|_| Error::InvalidProxy)?;
+                minreq_request = minreq_request.with_proxy(proxy);
+            }
+            let response = minreq_request.send_lazy().map_err(|e| {
+                debug!("GET {} failed: {}", url, e);
+                Error::Timeout
+            })?;
+            let announced = response.headers.get("content-length").and_then(|length| length.parse::<usize>().ok());
+            if announced.is_some_and(|length| length > request.max_body_bytes) {
+                debug!("GET {} -> {}: body too large ({} bytes announced)", url, response.status_code, announced.unwrap_or_default());
+                return Err(Error::ResponseTooLarge);
+            }
+
+            let status_code = response.status_code;
+            let headers = response.headers.clone();
+            let url = response.url.clone();
+            // read in blocks until the end of the stream (pages of several MB are common), one byte
+            // further than the limit to detect the bodies exceeding it
+            let mut body = Vec::with_capacity(announced.unwrap_or_default());
+            Read::take(response, request.max_body_bytes as u64 + 1)
+                .read_to_end(&mut body)
+                .map_err(|_| Error::Timeout)?;
+            if body.len() > request.max_body_bytes {
+                debug!("GET {} -> {}: body too large (more than {} bytes)", url, status_code, request.max_body_bytes);
+                return Err(Error::ResponseTooLarge);
+            }
+            Ok(RawResponse {
+                status_code,
+                headers,
+                url,
+                body,
+            })
+        }
+    }
+
+    /// Sends the requests with the blocking client of reqwest. Requires the "reqwest-backend" feature.  
+    /// The proxies of the environment are only used through the "proxy" feature, as with minreq.
+    #[cfg(feature = "reqwest-backend")]
+    #[derive(Debug, Clone)]
+    pub struct ReqwestBackend {
+        client: reqwest::blocking::Client,
+    }
+
+    #[cfg(feature = "reqwest-backend")]
+    impl Default for ReqwestBackend {
+        fn default() -> ReqwestBackend {
+            ReqwestBackend::new()
+        }
+    }
+
+    #[cfg(feature = "reqwest-backend")]
+    impl ReqwestBackend {
+        pub fn new() -> ReqwestBackend {
+            ReqwestBackend {
+                client: reqwest::blocking::Client::builder()
+                    .no_proxy()
+                    .build()
+                    .expect("the reqwest client cannot be initialized"),
+            }
+        }
+
+        /// Return the client to send a request through `proxy`.
+        #[cfg(feature = "proxy")]
+        fn client_for(&self, proxy: Option<&str>) -> Result<reqwest::blocking::Client, Error> {
+            let proxy = match proxy {
+                Some(proxy) => proxy,
+                None => return Ok(self.client.clone()),
+            };
+            // like minreq, only HTTP proxies are supported
+            let proxy = match proxy.split_once("://") {
+                Some(("http", _)) => proxy.to_string(),
+                Some(_) => return Err(Error::InvalidProxy),
+                None => format!("http://{}", proxy),
+            };
+            let proxy = reqwest::Proxy::all(proxy).map_err(|_| Error::InvalidProxy)?;
+            reqwest::blocking::Client::builder().proxy(proxy).build().map_err(|_| Error::InvalidProxy)
+        }
+
+        #[cfg(not(feature = "proxy"))]
+        fn client_for(&self, _proxy: Option<&str>) -> Result<reqwest::blocking::Client, Error> {
+            Ok(self.client.clone())
+        }
+    }
+
+    #[cfg(feature = "reqwest-backend")]
+    impl HttpClient for ReqwestBackend {
+        fn get(&self, request: &HttpRequest) -> Result<RawResponse, Error> {
+            use std::io::Read;
+
+            let url = request.url;
+            let mut reqwest_request = self.client_for(request.proxy.as_deref())?.get(url);
+            for (name, value) in &request.headers {
+                reqwest_request = reqwest_request.header(name.as_str(), value.as_str());
+            }
+            let response = reqwest_request.send().map_err(|e| {
+                debug!("GET {} failed: {}", url, e);
+                Error::Timeout
+            })?;
+            let status_code = i32::from(response.status().as_u16());
+            if response.content_length().is_some_and(|length| length > request.max_body_bytes as u64) {
+                debug!("GET {} -> {}: body too large ({:?} bytes announced)", url, status_code, response.content_length());
+                return Err(Error::ResponseTooLarge);
+            }
+
+            let headers = response
+                .headers()
+                .iter()
+                .map(|(name, value)| (name.as_str().to_string(), String::from_utf8_lossy(value.as_bytes()).into_owned()))
+                .collect();
+            let url = response.url().to_string();
+            let mut body = Vec::new();
+            response
+                .take(request.max_body_bytes as u64 + 1)
+                .read_to_end(&mut body)
+                .map_err(|_| Error::Timeout)?;
+            if body.len() > request.max_body_bytes {
+                debug!("GET {} -> {}: body too large (more than {} bytes)", url, status_code, request.max_body_bytes);
+                return Err(Error::ResponseTooLarge);
+            }
+            Ok(RawResponse {
+                status_code,
+                headers,
+                url,
+                body,
+            })
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use crate::client::Client;
+        use crate::mock::{http_response, MockServer};
+
+        #[cfg(feature = "reqwest-backend")]
+        #[test]
+        fn reqwest_backend() {
+            let server = MockServer::start(|request| {
+                if request.starts_with("GET /large ") {
+                    http_response(200, &[], &[b'a'; 2000])
+                } else {
+                    http_response(200, &[("Set-Cookie", "NID=1; path=/")], b"discord.gg/UNWEj54")
+                }
+            });
+            let client = Client::new().with_http_client(ReqwestBackend::new()).with_max_body_bytes(1000);
+            let response = client.request(&server.url, &[("Accept", "text/plain")]).unwrap();
+            assert_eq!(response.status_code, 200);
+            assert_eq!(response.headers["set-cookie"], "NID=1; path=/");
+            assert_eq!(client.cookie("NID").as_deref(), Some("1"));
+            assert_eq!(client.resolve(&server.url).unwrap(), vec!["https://discord.com/invite/UNWEj54"]);
+            assert!(matches!(client.request(&format!("{}/large", server.url), &[]), Err(Error::ResponseTooLarge)));
+
+            let requests = server.requests();
+            assert!(requests[0].contains("\r\nAccept: text/plain\r\n"));
+            assert!(requests[1].contains("\r\nCookie: NID=1\r\n"));
+        }
+
+        #[cfg(feature = "minreq-backend")]
+        #[test]
+        fn minreq_backend() {
+            let server = MockServer::start(|_| http_response(200, &[("Set-Cookie", "NID=1; path=/")], b"discord.gg/UNWEj54"));
+            let client = Client::new().with_http_client(MinreqBackend).with_max_body_bytes(10);
+            assert!(matches!(client.request(&server.url, &[]), Err(Error::ResponseTooLarge)));
+            let response = MinreqBackend
+                .get(&HttpRequest {
+                    url: &server.url,
+                    headers: vec![("Accept".to_string(), "text/plain".to_string())],
+                    max_body_bytes: 100,
+                    proxy: None,
+                })
+                .unwrap();
+            assert_eq!(response.headers["set-cookie"], "NID=1; path=/");
+            assert_eq!(response.body, b"discord.gg/UNWEj54");
+            assert!(server.requests()[1].contains("\r\nAccept: text/plain\r\n"));
+        }
+    }
+}
+
+/// Contains the Client, which keeps the state shared by successive requests.
+pub mod client {
+    use super::discord::{get_invite_code, invite_url, is_short_link, parse_og, parse_raw, Invite, OgPreview, Template, Widget};
+    use super::google::{self, SearchQueryBuilder};
+    use super::intermediary;
+    use super::backend::{default_http_client, HttpClient, HttpRequest};
+    use super::util::{backoff, host, is_in_domain, TokenBucket};
+    use super::Error;
+    use log::{debug, info, trace};
+    use serde_json::{from_str, Value};
+    use std::collections::{BTreeMap, HashMap};
+    use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+    use std::sync::Mutex;
+    use std::thread::{self, sleep};
+    use std::time::{Duration, Instant};
+
+    pub(crate) const DEFAULT_API_BASE: &str = "https://discord.com/api";
+    const DEFAULT_ACCEPT_LANGUAGE: &str = "en-US";
+    const USER_AGENT: &str = "Mozilla/5.0 (X11; Linux x86_64; rv:72.0) Gecko/20100101 Firefox/72.0";
+    /// The default maximum size of a response body: 16 MiB.
+    pub const DEFAULT_MAX_BODY_BYTES: usize = 16 * 1024 * 1024;
+    const RETRY_BASE_DELAY: Duration = Duration::from_secs(1);
+    const RETRY_MAX_DELAY: Duration = Duration::from_secs(30);
+
+    /// A response returned by [`Client::request`].
+    #[derive(Debug, Clone)]
+    pub struct RawResponse {
+        pub status_code: i32,
+        /// Header names are lowercase.
+        pub headers: HashMap<String, String>,
+        /// The url of the response, which differs from the requested one after a redirection.
+        pub url: String,
+        pub body: Vec<u8>,
+    }
+
+    impl RawResponse {
+        /// Return the body if it is valid UTF-8.
+        pub fn body_str(&self) -> Option<&str> {
+            std::str::from_utf8(&self.body).ok()
+        }
+
+        /// Whether the response looks like JSON: a JSON content type (if any) and a body starting with `{` or `[`.  
+        /// Discord sometimes answers with an HTML maintenance page and a 200 status.
+        fn is_json(&self) -> bool {
+            let json_type = self.headers.get("content-type").is_none_or(|t| t.contains("json"));
+            let json_body = matches!(self.body.iter().find(|b| !b.is_ascii_whitespace()), Some(b'{') | Some(b'['));
+            json_type && json_body
+        }
+    }
+
+    /// Produce the headers of a request: the headers common to every request (the user agent and the
+    /// cookies of the client), overridden by `headers`.
+    pub(crate) fn build_headers(client: &Client, headers: &[(&str, &str)]) -> Vec<(String, String)> {
+        let mut all = vec![("User-Agent".to_string(), USER_AGENT.to_string())];
+        let cookies: Vec<String> = client
+            .cookies
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(name, value)| format!("{}={}", name, value))
+            .collect();
+        if !cookies.is_empty() {
+            all.push(("Cookie".to_string(), cookies.join("; ")));
+        }
+        for (name, value) in headers {
+            all.retain(|(default, _)| !default.eq_ignore_ascii_case(name));
+            all.push((name.to_string(), value.to_string()));
+        }
+        all
+    }
+
+    /// The rate limit buckets reported by Discord in the `X-RateLimit-*` headers.  
+    /// Several routes can share a bucket, which is only known after a first response.
+    #[derive(Debug, Default)]
+    struct Buckets {
+        /// route -> bucket id
+        routes: HashMap<String, String>,
+        /// bucket id -> (remaining requests, reset time)
+        buckets: HashMap<String, (u64, Instant)>,
+    }
+
+    impl Buckets {
+        /// Return the time to wait before a request to `route`, if its bucket is exhausted.
+        fn delay(&self, route: &str) -> Option<Duration> {
+            let bucket = self.routes.get(route)?;
+            match self.buckets.get(bucket)? {
+                (0, reset) => reset.checked_duration_since(Instant::now()),
+                _ => None,
+            }
+        }
+
+        /// Record the rate limit headers of a response to `route`, and return its reset delay.
+        fn update(&mut self, route: &str, headers: &HashMap<String, String>) -> Option<Duration> {
+            let reset_after = headers
+                .get("x-ratelimit-reset-after")
+                .and_then(|reset_after| reset_after.parse::<f64>().ok())
+                .filter(|reset_after| reset_after.is_finite())
+                .map(|reset_after| Duration::from_secs_f64(reset_after.clamp(0.0, 3600.0)));
+            let remaining = headers.get("x-ratelimit-remaining").and_then(|remaining| remaining.parse().ok());
+            let bucket = headers
+                .get("x-ratelimit-bucket")
+                .cloned()
+                .or_else(|| self.routes.get(route).cloned())
+                .unwrap_or_else(|| route.to_string());
+            if let (Some(remaining), Some(reset_after)) = (remaining, reset_after) {
+                self.routes.insert(route.to_string(), bucket.clone());
+                self.buckets.insert(bucket, (remaining, Instant::now() + reset_after));
+            }
+            reset_after
+        }
+    }
+
+    /// Stops sending requests to the hosts failing repeatedly.
+    #[derive(Debug)]
+    struct CircuitBreaker {
+        max_failures: u32,
+        cooldown: Duration,
+        /// host -> (consecutive failures, end of the cooldown)
+        hosts: Mutex<HashMap<String, (u32, Option<Instant>)>>,
+    }
+
+    impl CircuitBreaker {
+        fn is_open(&self, host: &str) -> bool {
+            let hosts = self.hosts.lock().unwrap();
+            let open_until = hosts.get(host).and_then(|(_, open_until)| *open_until);
+            open_until.is_some_and(|open_until| Instant::now() < open_until)
+        }
+
+        /// Record the outcome of a request. After the cooldown, a single failure opens the circuit again.
+        fn record(&self, host: &str, failed: bool) {
+            let mut hosts = self.hosts.lock().unwrap();
+            if !failed {
+                hosts.remove(host);
+                return;
+            }
+            let (failures, open_until) = hosts.entry(host.to_string()).or_insert((0, None));
+            *failures += 1;
+            if *failures >= self.max_failures {
+                debug!("{} failed {} times in a row, pausing requests for {:?}", host, failures, self.cooldown);
+                *open_until = Some(Instant::now() + self.cooldown);
+            }
+        }
+    }
+
+    /// The outcome of an invite request.
+    enum Fetched {
+        /// The invite, its raw payload and its `ETag`
+        Modified(Box<Invite>, Value, Option<String>),
+        NotModified,
+        NotFound,
+    }
+
+    /// A client that can be reused for many requests.  
+    /// When Discord reports a global rate limit, every following request made with the same client
+    /// waits until the limit is lifted. Requests to a route whose rate limit bucket is exhausted
+    /// (`X-RateLimit-Remaining: 0`) also wait for the bucket to reset, which prevents most 429 responses.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use discord_finder::client::Client;
+    ///
+    /// let client = Client::new();
+    /// for url in &["https://discord.gg/UNWEj54", "https://discord.gg/8j8b2xR"] {
+    ///     println!("{:?}", client.fetch_invite(url));
+    /// }
+    /// ```
+    #[derive(Debug)]
+    pub struct Client {
+        api_base: String,
+        accept_language: String,
+        max_body_bytes: usize,
+        rate_limit: Option<TokenBucket>,
+        pub(crate) follow_canonical: bool,
+        pub(crate) desktop_urls: bool,
+        cookies: Mutex<BTreeMap<String, String>>,
+        global_cooldown: Mutex<Option<Instant>>,
+        buckets: Mutex<Buckets>,
+        request_count: AtomicU64,
+        http: Box<dyn HttpClient>,
+        #[cfg(feature = "proxy")]
+        proxy: Option<String>,
+        /// The variables read by [`Client::proxy_for`] instead of the environment of the process, if set.
+        #[cfg(feature = "proxy")]
+        proxy_env: Option<HashMap<String, String>>,
+        allowed_domains: Vec<String>,
+        denied_domains: Vec<String>,
+        circuit_breaker: Option<CircuitBreaker>,
+        dry_run: bool,
+        /// The urls that would have been requested in dry run mode
+        planned: Mutex<Vec<String>>,
+        bot_token: Option<Secret>,
+        retries: Retries,
+    }
+
+    #[derive(Debug)]
+    struct Retries {
+        max_retries: u32,
+        base_delay: Duration,
+        /// The retries left for all the requests of the client
+        budget: Option<AtomicU64>,
+    }
+
+    /// A string hidden from the `Debug` output, so that logging a client does not leak its token.
+    struct Secret(String);
+
+    impl std::fmt::Debug for Secret {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            f.write_str("Secret(..)")
+        }
+    }
+
+    impl Default for Client {
+        fn default() -> Client {
+            Client {
+                api_base: DEFAULT_API_BASE.to_string(),
+                accept_language: DEFAULT_ACCEPT_LANGUAGE.to_string(),
+                max_body_bytes: DEFAULT_MAX_BODY_BYTES,
+                rate_limit: None,
+                follow_canonical: false,
+                desktop_urls: false,
+                cookies: Mutex::new(BTreeMap::new()),
+                global_cooldown: Mutex::new(None),
+                buckets: Mutex::new(Buckets::default()),
+                request_count: AtomicU64::new(0),
+                http: default_http_client(),
+                #[cfg(feature = "proxy")]
+                proxy: None,
+                #[cfg(feature = "proxy")]
+                proxy_env: None,
+                allowed_domains: Vec::new(),
+                denied_domains: Vec::new(),
+                circuit_breaker: None,
+                dry_run: false,
+                planned: Mutex::new(Vec::new()),
+                bot_token: None,
+                retries: Retries {
+                    max_retries: 0,
+                    base_delay: RETRY_BASE_DELAY,
+                    budget: None,
+                },
+            }
+        }
+    }
+
+    impl Client {
+        pub fn new() -> Client {
+            Client::default()
+        }
+
+        /// Send the requests with another HTTP implementation (see [`backend`](crate::backend)).
+        pub fn with_http_client<H: HttpClient + 'static>(mut self, http: H) -> Client {
+            self.http = Box::new(http);
+            self
+        }
+
+        /// Send the Discord API requests to another base url (a proxy or a mock server for example).  
+        /// The default is `https://discord.com/api`.
+        pub fn with_api_base(mut self, api_base: &str) -> Client {
+            self.api_base = api_base.trim_end_matches('/').to_string();
+            self
+        }
+
+        /// Set the `Accept-Language` header of the requests sent to Discord (API requests and invite pages).
+        /// Other requests, such as searches and resolved pages, are sent without it.  
+        /// Discord localizes some strings of its responses, the default `en-US` keeps them consistent.
+        pub fn with_accept_language(mut self, accept_language: &str) -> Client {
+            self.accept_language = accept_language.to_string();
+            self
+        }
+
+        /// Send at most `requests_per_second` Discord API requests per second, with bursts of up to `burst`
+        /// requests. The limit is shared by all the threads using this client.  
+        /// By default, only the rate limits reported by Discord are respected.
+        pub fn with_rate_limit(mut self, requests_per_second: f64, burst: u32) -> Client {
+            self.rate_limit = Some(TokenBucket::new(requests_per_second, burst));
+            self
+        }
+
+        /// Send again up to `max_retries` times the requests failing with a timeout or a server error,
+        /// waiting longer after each failure (`base_delay`, then twice as long...). Disabled by default.  
+        /// See also [`Client::with_retry_budget`].
+        pub fn with_retries(mut self, max_retries: u32, base_delay: Duration) -> Client {
+            self.retries.max_retries = max_retries;
+            self.retries.base_delay = base_delay;
+            self
+        }
+
+        /// Limit the total number of retries of all the requests of this client (and of the crawlers and
+        /// threads using it), so that a bad network cannot multiply the number of requests.
+        /// Once the budget is exhausted, failures are returned immediately.
+        pub fn with_retry_budget(mut self, budget: u64) -> Client {
+            self.retries.budget = Some(AtomicU64::new(budget));
+            self
+        }
+
+        /// Authenticate the Discord API requests with a bot token (`Authorization: Bot {token}`), which raises
+        /// the rate limits of invite fetches.  
+        /// This is optional: the endpoints used by this crate work without authentication. The token is only sent
+        /// to the Discord API, never to the pages being resolved.
+        pub fn with_bot_token(mut self, token: &str) -> Client {
+            self.bot_token = Some(Secret(token.to_string()));
+            self
+        }
+
+        /// In dry run mode, no request is sent: the url of each request is logged and recorded (see
+        /// [`Client::planned_requests`]) and an empty response is returned instead. Searches and resolutions
+        /// return no results, and fetched invites only contain their code.  
+        /// This validates the queries and the pacing of a crawler without network activity. Disabled by default.
+        pub fn with_dry_run(mut self, dry_run: bool) -> Client {
+            self.dry_run = dry_run;
+            self
+        }
+
+        /// The urls that would have been requested by this client in dry run mode, in order.
+        pub fn planned_requests(&self) -> Vec<String> {
+            self.planned.lock().unwrap().clone()
+        }
+
+        /// After `max_failures` consecutive failures (timeouts and server errors) of a host, fail the requests
+        /// to this host with [`Error::CircuitOpen`] during `cooldown`, instead of waiting for it again.  
+        /// After the cooldown, requests are sent again and a single failure pauses them for another cooldown.
+        /// Disabled by default.
+        pub fn with_circuit_breaker(mut self, max_failures: u32, cooldown: Duration) -> Client {
+            self.circuit_breaker = Some(CircuitBreaker {
+                max_failures: max_failures.max(1),
+                cooldown,
+                hosts: Mutex::new(HashMap::new()),
+            });
+            self
+        }
+
+        /// When a page references no invite, load the page of its `<link rel="canonical">` tag instead
+        /// (never more than one hop). Disabled by default.
+        pub fn with_follow_canonical(mut self, follow_canonical: bool) -> Client {
+            self.follow_canonical = follow_canonical;
+            self
+        }
+
+        /// Load the desktop version of AMP and mobile pages instead of the page itself
+        /// (see [`desktop_url`](crate::intermediary::desktop_url)). Disabled by default.
+        pub fn with_desktop_urls(mut self, desktop_urls: bool) -> Client {
+            self.desktop_urls = desktop_urls;
+            self
+        }
+
+        /// Abort the requests whose body exceeds `max_body_bytes` with [`Error::ResponseTooLarge`],
+        /// so that a hostile server cannot exhaust the memory.  
+        /// The default is [`DEFAULT_MAX_BODY_BYTES`].
+        pub fn with_max_body_bytes(mut self, max_body_bytes: usize) -> Client {
+            self.max_body_bytes = max_body_bytes;
+            self
+        }
+
+        /// Send every request through an HTTP proxy (`[http://][user[:password]@]host[:port]`).
+        /// Requires the "proxy" feature.  
+        /// This proxy takes precedence over the environment variables (see [`Client::proxy_for`]).
+        #[cfg(feature = "proxy")]
+        pub fn with_proxy(mut self, proxy: &str) -> Client {
+            self.proxy = Some(proxy.to_string());
+            self
+        }
+
+        /// Read the proxy variables from these pairs instead of the environment of the process
+        /// (see [`Client::proxy_for`]). Requires the "proxy" feature.
+        #[cfg(feature = "proxy")]
+        pub fn with_proxy_env(mut self, vars: &[(&str, &str)]) -> Client {
+            self.proxy_env = Some(vars.iter().map(|(name, value)| (name.to_string(), value.to_string())).collect());
+            self
+        }
+
+        /// Return the proxy a request to `url` goes through. Requires the "proxy" feature.  
+        /// Like curl, the proxy given to [`Client::with_proxy`] is used first, then `https_proxy` (or `HTTPS_PROXY`)
+        /// for https urls and `http_proxy` (or `HTTP_PROXY`) for the others, and finally `all_proxy`
+        /// (or `ALL_PROXY`). The variables are read at each request.  
+        /// No proxy is used for the hosts listed in `no_proxy` (or `NO_PROXY`): a comma-separated list of
+        /// hosts, also matching their subdomains, or `*` to disable the proxies entirely.
+        #[cfg(feature = "proxy")]
+        pub fn proxy_for(&self, url: &str) -> Option<String> {
+            let lookup = |name: &str| match &self.proxy_env {
+                Some(vars) => vars.get(name).cloned(),
+                None => std::env::var(name).ok(),
+            };
+            let var = |name: &str| {
+                lookup(name)
+                    .or_else(|| lookup(&name.to_ascii_uppercase()))
+                    .filter(|value| !value.is_empty())
+            };
+            if let Some(no_proxy) = var("no_proxy") {
+                let host = host(url);
+                let bypassed = no_proxy
+                    .split(',')
+                    .map(str::trim)
+                    .any(|entry| entry == "*" || (!entry.is_empty() && is_in_domain(&host, entry)));
+                if bypassed {
+                    return None;
+                }
+            }
+            let scheme_proxy = if url.starts_with("https://") {
+                var("https_proxy")
+            } else {
+                var("http_proxy")
+            };
+            self.proxy.clone().or(scheme_proxy).or_else(|| var("all_proxy"))
+        }
+
+        /// Only keep the search results whose host is one of these domains or one of their subdomains.  
+        /// By default, results from every domain are kept.
+        pub fn with_allow_domains(mut self, domains: &[&str]) -> Client {
+            self.allowed_domains = domains.iter().map(|domain| domain.to_string()).collect();
+            self
+        }
+
+        /// Drop the search results whose host is one of these domains or one of their subdomains,
+        /// even if they are allowed by [`Client::with_allow_domains`].
+        pub fn with_deny_domains(mut self, domains: &[&str]) -> Client {
+            self.denied_domains = domains.iter().map(|domain| domain.to_string()).collect();
+            self
+        }
+
+        /// Return true if a search result passes the domain filters of this client.
+        pub(crate) fn accepts_domain(&self, url: &str) -> bool {
+            let host = host(url);
+            let allowed = self.allowed_domains.is_empty()
+                || self.allowed_domains.iter().any(|domain| is_in_domain(&host, domain));
+            allowed && !self.denied_domains.iter().any(|domain| is_in_domain(&host, domain))
+        }
+
+        /// Add a cookie to send with every request, for example a session or consent cookie.  
+        /// Cookies set by the responses are then stored and sent with the following requests.
+        pub fn with_cookie(self, name: &str, value: &str) -> Client {
+            self.cookies
+                .lock()
+                .unwrap()
+                .insert(name.to_string(), value.to_string());
+            self
+        }
+
+        /// Return the current value of a cookie.
+        pub fn cookie(&self, name: &str) -> Option<String> {
+            self.cookies.lock().unwrap().get(name).cloned()
+        }
+
+        /// Store the cookie of a `Set-Cookie` header, ignoring its attributes.
+        fn store_cookie(&self, set_cookie: &str) {
+            let pair = set_cookie.split(';').next().unwrap_or_default();
+            if let Some((name, value)) = pair.split_once('=') {
+                let mut cookies = self.cookies.lock().unwrap();
+                let expired = set_cookie.to_ascii_lowercase().contains("max-age=0");
+                if expired {
+                    cookies.remove(name.trim());
+                } else {
+                    cookies.insert(name.trim().to_string(), value.trim().to_string());
+                }
+            }
+        }
+
+        /// Loads a discord.gg page and produces an Invite struct.
+        pub fn fetch_invite(&self, url: &str) -> Result<Invite, Error> {
+            self.fetch_invite_raw(url).map(|(invite, _raw)| invite)
+        }
+
+        /// Same as [`Client::fetch_invite`] but also returns the raw JSON payload sent by Discord.
+        pub fn fetch_invite_raw(&self, url: &str) -> Result<(Invite, Value), Error> {
+            match self.fetch_invite_with_etag(url, None)? {
+                Fetched::Modified(invite, raw, _etag) => Ok((*invite, raw)),
+                Fetched::NotModified | Fetched::NotFound => Err(Error::InvalidResponse),
+            }
+        }
+
+        /// Same as [`Client::fetch_invite`] but returns `Ok(None)` when the invite does not exist (a 404),
+        /// which makes pruning dead invites easier.  
+        /// Only network failures and unexpected responses are errors.
+        pub fn fetch_invite_opt(&self, url: &str) -> Result<Option<Invite>, Error> {
+            match self.fetch_invite_with_etag(url, None)? {
+                Fetched::Modified(invite, _raw, _etag) => Ok(Some(*invite)),
+                Fetched::NotFound => Ok(None),
+                Fetched::NotModified => Err(Error::InvalidResponse),
+            }
+        }
+
+        /// Fetch an invite only if it changed since the version identified by `etag`, saving bandwidth.  
+        /// Returns `Ok(None)` if Discord answered that the invite was not modified, otherwise returns
+        /// the invite and its new `ETag`, to use for the next call.
+        /// Without `etag`, the request is unconditional.
+        pub fn fetch_invite_if_modified(
+            &self,
+            url: &str,
+            etag: Option<&str>,
+        ) -> Result<Option<(Invite, Option<String>)>, Error> {
+            match self.fetch_invite_with_etag(url, etag)? {
+                Fetched::Modified(invite, _raw, etag) => Ok(Some((*invite, etag))),
+                Fetched::NotModified => Ok(None),
+                Fetched::NotFound => Err(Error::InvalidResponse),
+            }
+        }
+
+        fn fetch_invite_with_etag(&self, url: &str, etag: Option<&str>) -> Result<Fetched, Error> {
+            let invite_code = match get_invite_code(url) {
+                Some(code) => code,
+                None => return Err(Error::InvalidResponse),
+            };
+            let url = invite_url(&self.api_base, invite_code, true, false);
+
+            let mut headers = vec![
+                ("Accept", "text/html"),
+                ("DNT", "1"),
+                ("Connection", "keep-alive"),
+                ("Upgrade-Insecure-Requests", "1"),
+                ("TE", "Trailers"),
+            ];
+            if let Some(etag) = etag {
+                headers.push(("If-None-Match", etag));
+            }
+
+            let response = self.api_request("invites", &url, &headers)?;
+            if self.dry_run {
+                let placeholder = from_str(&format!(r#"{{"code": "{}"}}"#, invite_code)).map_err(|_| Error::ParseError)?;
+                return Ok(Fetched::Modified(Box::new(placeholder), Value::Null, None));
+            }
+            if response.status_code == 200 {
+                if !response.is_json() {
+                    return Err(Error::ServiceUnavailable);
+                }
+                if let Some(body) = response.body_str() {
+                    trace!("invite payload: {}", body);
+
+                    let (invite, raw) = parse_raw(body)?;
+                    Ok(Fetched::Modified(Box::new(invite), raw, response.headers.get("etag").cloned()))
+                } else {
+                    Err(Error::InvalidResponse)
+                }
+            } else if response.status_code == 304 && etag.is_some() {
+                Ok(Fetched::NotModified)
+            } else if response.status_code == 404 {
+                Ok(Fetched::NotFound)
+            } else if response.status_code == 429 {
+                Err(self.handle_rate_limit(response.body_str().unwrap_or_default()))
+            } else {
+                Err(Error::InvalidResponse)
+            }
+        }
+
+        /// Loads a server template from its code (see [`get_template_code`](crate::discord::get_template_code)).
+        pub fn fetch_template(&self, code: &str) -> Result<Template, Error> {
+            let url = format!("{}/v6/guilds/templates/{}", self.api_base, code);
+            let response = self.api_request("templates", &url, &[])?;
+            match response.status_code {
+                200 if !response.is_json() => Err(Error::ServiceUnavailable),
+                200 => match response.body_str().map(from_str) {
+                    Some(Ok(template)) => Ok(template),
+                    _ => Err(Error::ParseError),
+                },
+                429 => Err(self.handle_rate_limit(response.body_str().unwrap_or_default())),
+                _ => Err(Error::InvalidResponse),
+            }
+        }
+
+        /// Follow a Discord short link (see [`resolve_short_link`](crate::discord::resolve_short_link)).
+        pub fn resolve_short_link(&self, url: &str) -> Result<Option<String>, Error> {
+            if !is_short_link(url) {
+                return Ok(None);
+            }
+            let response = self.request(url, &[])?;
+            match get_invite_code(&response.url) {
+                Some(code) => Ok(Some(format!("https://discord.com/invite/{}", code))),
+                None => {
+                    debug!("{} does not lead to an invite but to {}", url, response.url);
+                    Ok(None)
+                }
+            }
+        }
+
+        /// Loads the HTML page of an invite and parses its OpenGraph preview (see [`Invite::fetch_og`]).  
+        /// Fails with [`Error::ParseError`] if the page has no preview, as for expired invites.
+        pub fn fetch_og(&self, url: &str) -> Result<OgPreview, Error> {
+            let invite_code = get_invite_code(url).ok_or(Error::InvalidResponse)?;
+            let url = format!("https://discord.com/invite/{}", invite_code);
+            let response = self.request(&url, &[("Accept", "text/html"), ("Accept-Language", &self.accept_language)])?;
+            match response.status_code {
+                200 => match response.body_str().map(parse_og) {
+                    Some(Some(preview)) => Ok(preview),
+                    _ => Err(Error::ParseError),
+                },
+                429 => Err(Error::Blocked),
+                code if code >= 500 => Err(Error::ServerError(code)),
+                _ => Err(Error::InvalidResponse),
+            }
+        }
+
+        /// Loads the widget of a guild (see [`fetch_widget`](crate::discord::fetch_widget)).
+        pub fn fetch_widget(&self, guild_id: &str) -> Result<Widget, Error> {
+            let url = format!("{}/v6/guilds/{}/widget.json", self.api_base, guild_id);
+            let response = self.api_request("widget", &url, &[])?;
+            match response.status_code {
+                200 if !response.is_json() => Err(Error::ServiceUnavailable),
+                200 => match response.body_str().map(from_str) {
+                    Some(Ok(widget)) => Ok(widget),
+                    _ => Err(Error::ParseError),
+                },
+                403 => Err(Error::WidgetDisabled),
+                429 => Err(self.handle_rate_limit(response.body_str().unwrap_or_default())),
+                _ => Err(Error::InvalidResponse),
+            }
+        }
+
+        /// Send a GET request with the given headers and return the whole response, headers included.  
+        /// This is the method used by the other requests, exposed to help debugging blocked or failing requests.  
+        /// The user agent and the stored cookies are sent unless `headers` overrides them, and the cookie of
+        /// the response is stored (only the last `Set-Cookie` header of a response is kept).
+        pub fn request(&self, url: &str, headers: &[(&str, &str)]) -> Result<RawResponse, Error> {
+            if self.dry_run {
+                info!("dry run: GET {}", url);
+                self.planned.lock().unwrap().push(url.to_string());
+                return Ok(RawResponse {
+                    status_code: 200,
+                    headers: HashMap::new(),
+                    url: url.to_string(),
+                    body: Vec::new(),
+                });
+            }
+            #[cfg(feature = "proxy")]
+            let proxy = self.proxy_for(url);
+            #[cfg(not(feature = "proxy"))]
+            let proxy = None;
+            let request = HttpRequest {
+                url,
+                headers: build_headers(self, headers),
+                max_body_bytes: self.max_body_bytes,
+                proxy,
+            };
+            let host = host(url);
+            let mut attempt = 0;
+            let response = loop {
+                if let Some(breaker) = &self.circuit_breaker {
+                    if breaker.is_open(&host) {
+                        debug!("GET {} skipped: too many failures of {}", url, host);
+                        return Err(Error::CircuitOpen);
+                    }
+                }
+                trace!("GET {}", url);
+                self.request_count.fetch_add(1, Ordering::Relaxed);
+                let response = self.http.get(&request);
+                let failed = match &response {
+                    Ok(response) => response.status_code >= 500,
+                    Err(error) => matches!(error, Error::Timeout),
+                };
+                if let Some(breaker) = &self.circuit_breaker {
+                    breaker.record(&host, failed);
+                }
+                if !failed || attempt >= self.retries.max_retries || !self.take_retry() {
+                    break response;
+                }
+                debug!("GET {} failed, retry {}/{}", url, attempt + 1, self.retries.max_retries);
+                sleep(backoff(attempt, self.retries.base_delay, RETRY_MAX_DELAY));
+                attempt += 1;
+            };
+            let response = response?;
+            if let Some(set_cookie) = response.headers.get("set-cookie") {
+                self.store_cookie(set_cookie);
+            }
+            debug!("GET {} -> {} ({} bytes)", response.url, response.status_code, response.body.len());
+            Ok(response)
+        }
+
+        /// Consume one retry of the budget, if there is one left.
+        fn take_retry(&self) -> bool {
+            match &self.retries.budget {
+                Some(budget) => budget
+                    .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |left| left.checked_sub(1))
+                    .is_ok(),
+                None => true,
+            }
+        }
+
+        /// The number of retries left in the budget set by [`Client::with_retry_budget`], if any.
+        pub fn retries_left(&self) -> Option<u64> {
+            self.retries.budget.as_ref().map(|budget| budget.load(Ordering::SeqCst))
+        }
+
+        /// The number of HTTP requests sent by this client since its creation, failed ones included.
+        pub fn request_count(&self) -> u64 {
+            self.request_count.load(Ordering::Relaxed)
+        }
+
+        /// Search Google with the settings of this client (see [`google::search_query`]).  
+        /// The results are filtered by [`Client::with_allow_domains`] and [`Client::with_deny_domains`].
+        pub fn search(&self, query: &SearchQueryBuilder) -> Result<Vec<String>, Error> {
+            google::search_url(self, &query.build())
+        }
+
+        /// Load a page with the settings of this client and return the invite links it references
+        /// (see [`intermediary::resolve`]).
+        pub fn resolve(&self, url: &str) -> Result<Vec<String>, Error> {
+            intermediary::resolve_with(self, url)
+        }
+
+        /// Same as [`Client::resolve`] but only returns the invites that exist
+        /// (see [`intermediary::resolve_validated`]).
+        pub fn resolve_validated(&self, url: &str, delay: Duration) -> Result<Vec<String>, Error> {
+            intermediary::resolve_validated_with(self, url, delay)
+        }
+
+        /// Read the body of a 429 response and start the global cooldown if the limit is global.  
+        /// Discord API v6 expresses `retry_after` in milliseconds.
+        fn handle_rate_limit(&self, body: &str) -> Error {
+            let body: Value = from_str(body).unwrap_or_default();
+            let retry_after = body["retry_after"].as_f64().unwrap_or(0.0).max(0.0);
+            let retry_after = Duration::from_secs_f64(retry_after / 1000.0);
+            if body["global"].as_bool() == Some(true) {
+                self.extend_global_cooldown(retry_after);
+            }
+            debug!("rate limited for {:?} (global: {})", retry_after, body["global"]);
+            Error::RateLimited(retry_after)
+        }
+
+        /// Return the time left before the global rate limit is lifted, if any.
+        pub fn global_cooldown(&self) -> Option<Duration> {
+            let global_cooldown = self.global_cooldown.lock().unwrap();
+            global_cooldown.and_then(|end| end.checked_duration_since(Instant::now()))
+        }
+
+        /// Wait until a Discord API request is allowed by the global cooldown, the bucket of its route and the rate limit.
+        fn throttle(&self, route: &str) {
+            if let Some(remaining) = self.global_cooldown() {
+                sleep(remaining);
+            }
+            if let Some(remaining) = self.buckets.lock().unwrap().delay(route) {
+                debug!("bucket of {} exhausted, waiting {:?}", route, remaining);
+                sleep(remaining);
+            }
+            if let Some(rate_limit) = &self.rate_limit {
+                rate_limit.acquire();
+            }
+        }
+
+        /// Send a Discord API request once it is allowed, and record the rate limit headers of the response.  
+        /// `route` identifies the endpoint, whatever its parameters.
+        fn api_request(&self, route: &str, url: &str, headers: &[(&str, &str)]) -> Result<RawResponse, Error> {
+            self.throttle(route);
+            let authorization;
+            let mut headers = headers.to_vec();
+            headers.insert(0, ("Accept-Language", &self.accept_language));
+            if let Some(Secret(token)) = &self.bot_token {
+                authorization = format!("Bot {}", token);
+                headers.push(("Authorization", &authorization));
+            }
+            let response = self.request(url, &headers)?;
+            let reset_after = self.buckets.lock().unwrap().update(route, &response.headers);
+            let global = response.headers.get("x-ratelimit-global").is_some_and(|global| global == "true");
+            if let (true, Some(reset_after)) = (global, reset_after) {
+                self.extend_global_cooldown(reset_after);
+            }
+            Ok(response)
+        }
+
+        fn extend_global_cooldown(&self, duration: Duration) {
+            let mut global_cooldown = self.global_cooldown.lock().unwrap();
+            let end = Instant::now() + duration;
+            if global_cooldown.map(|current| current < end).unwrap_or(true) {
+                *global_cooldown = Some(end);
+            }
+        }
+
+        /// Fetch many invites from their codes with up to `concurrency` threads, and return the results
+        /// in the order of `codes`.  
+        /// The threads share the rate limit of the client (see [`Client::with_rate_limit`]) and its global cooldown.
+        pub fn fetch_many_concurrent<S: AsRef<str> + Sync>(
+            &self,
+            codes: &[S],
+            concurrency: usize,
+        ) -> Vec<Result<Invite, Error>> {
+            self.fetch_many_concurrent_with_progress(codes, concurrency, |_, _| ())
+        }
+
+        /// Same as [`Client::fetch_many_concurrent`] but calls `progress(done, total)` after each fetch,
+        /// to render a progress bar. `done` increases by one at each call, whatever the thread.
+        pub fn fetch_many_concurrent_with_progress<S, F>(
+            &self,
+            codes: &[S],
+            concurrency: usize,
+            progress: F,
+        ) -> Vec<Result<Invite, Error>>
+        where
+            S: AsRef<str> + Sync,
+            F: FnMut(usize, usize) + Send,
+        {
+            let next = AtomicUsize::new(0);
+            let results: Mutex<Vec<Option<Result<Invite, Error>>>> =
+                Mutex::new((0..codes.len()).map(|_| None).collect());
+            // the number of fetches done and the callback
+            let progress = Mutex::new((0, progress));
+            thread::scope(|scope| {
+                for _ in 0..concurrency.clamp(1, codes.len().max(1)) {
+                    scope.spawn(|| loop {
+                        let i = next.fetch_add(1, Ordering::Relaxed);
+                        let code = match codes.get(i) {
+                            Some(code) => code.as_ref(),
+                            None => break,
+                        };
+                        let result = self.fetch_invite(&format!("https://discord.gg/{}", code));
+                        results.lock().unwrap()[i] = Some(result);
+                        let mut progress = progress.lock().unwrap();
+                        let (done, callback) = &mut *progress;
+                        *done += 1;
+                        callback(*done, codes.len());
+                    });
+                }
+            });
+            results
+                .into_inner()
+                .unwrap()
+                .into_iter()
+                .map(|result| result.unwrap_or(Err(Error::Timeout)))
+                .collect()
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use crate::mock::{http_response, MockServer};
+
+        const INVITE: &str = r#"{
+            "code": "UNWEj54",
+            "guild": {"id": "1", "name": "Server", "verification_level": 0},
+            "channel": {"id": "2", "type": 0},
+            "approximate_member_count": 10,
+            "approximate_presence_count": 2
+        }"#;
+
+        #[test]
+        fn custom_api_base() {
+            let server = MockServer::start(|_| http_response(200, &[], INVITE.as_bytes()));
+            let client = Client::new().with_api_base(&format!("{}/api/", server.url));
+            let invite = client.fetch_invite("https://discord.gg/UNWEj54").unwrap();
+            assert_eq!(invite.code, "UNWEj54");
+
+            let requests = server.requests();
+            assert_eq!(requests.len(), 1);
+            assert!(requests[0].starts_with("GET /api/v6/invites/UNWEj54?with_counts=true HTTP/1.1\r\n"));
+        }
+
+        #[test]
+        fn accept_language() {
+            let server = MockServer::start(|_| http_response(200, &[], INVITE.as_bytes()));
+            let client = Client::new().with_api_base(&server.url);
+            client.fetch_invite("https://discord.gg/UNWEj54").unwrap();
+            let client = client.with_accept_language("fr-FR");
+            client.fetch_invite("https://discord.gg/UNWEj54").unwrap();
+
+            client.request(&server.url, &[]).unwrap();
+
+            let requests = server.requests();
+            assert!(requests[0].starts_with("GET /v6/invites/UNWEj54?with_counts=true HTTP/1.1\r\n"));
+            assert!(requests[0].contains("\r\nAccept-Language: en-US\r\n"));
+            assert!(requests[1].starts_with("GET /v6/invites/UNWEj54?with_counts=true HTTP/1.1\r\n"));
+            assert!(requests[1].contains("\r\nAccept-Language: fr-FR\r\n"));
+            // only the requests sent to Discord are localized
+            assert!(!requests[2].contains("Accept-Language"));
+        }
+
+        #[test]
+        fn template_endpoint() {
+            let server = MockServer::start(|_| {
+                http_response(
+                    200,
+                    &[],
+                    br#"{"code": "hgM48av5Q69A", "name": "Gaming", "description": null, "source_guild_id": "1"}"#,
+                )
+            });
+            let template = Client::new().with_api_base(&server.url).fetch_template("hgM48av5Q69A").unwrap();
+            assert_eq!(template.name, "Gaming");
+            assert!(server.requests()[0].starts_with("GET /v6/guilds/templates/hgM48av5Q69A HTTP/1.1"));
+        }
+
+        #[test]
+        fn widget_endpoint() {
+            let server = MockServer::start(|request| {
+                if request.starts_with("GET /v6/guilds/1/") {
+                    http_response(
+                        200,
+                        &[("Content-Type", "application/json")],
+                        br#"{
+                            "id": "1",
+                            "name": "Server",
+                            "instant_invite": "https://discord.com/invite/UNWEj54",
+                            "channels": [{"id": "10", "name": "General", "position": 0}],
+                            "members": [{"id": "0", "username": "ferris", "discriminator": "0000", "avatar": null,
+                                "status": "online", "avatar_url": "https://cdn.discordapp.com/widget-avatars/x/y"}],
+                            "presence_count": 1
+                        }"#,
+                    )
+                } else {
+                    http_response(403, &[], br#"{"message": "Widget Disabled", "code": 50004}"#)
+                }
+            });
+            let client = Client::new().with_api_base(&server.url);
+            let widget = client.fetch_widget("1").unwrap();
+            assert_eq!(widget.name, "Server");
+            assert_eq!(widget.instant_invite.as_deref(), Some("https://discord.com/invite/UNWEj54"));
+            assert_eq!(widget.channels[0].name, "General");
+            assert_eq!(widget.members[0].status, "online");
+            assert_eq!(widget.presence_count, 1);
+            assert!(server.requests()[0].starts_with("GET /v6/guilds/1/widget.json HTTP/1.1"));
+
+            assert!(matches!(client.fetch_widget("2"), Err(Error::WidgetDisabled)));
+        }
+
+        #[test]
+        fn dead_invites_are_not_errors() {
+            let server = MockServer::start(|request| {
+                if request.starts_with("GET /v6/invites/UNWEj54") {
+                    http_response(200, &[], INVITE.as_bytes())
+                } else if request.starts_with("GET /v6/invites/limited") {
+                    http_response(429, &[], br#"{"retry_after": 1, "global": false}"#)
+                } else {
+                    http_response(404, &[], br#"{"message": "Unknown Invite", "code": 10006}"#)
+                }
+            });
+            let client = Client::new().with_api_base(&server.url);
+            assert_eq!(client.fetch_invite_opt("https://discord.gg/UNWEj54").unwrap().unwrap().code, "UNWEj54");
+            assert!(client.fetch_invite_opt("https://discord.gg/dead").unwrap().is_none());
+            assert!(matches!(client.fetch_invite_opt("https://discord.gg/limited"), Err(Error::RateLimited(_))));
+            assert!(matches!(client.fetch_invite("https://discord.gg/dead"), Err(Error::InvalidResponse)));
+        }
+
+        #[test]
+        fn maintenance_page() {
+            let server = MockServer::start(|request| {
+                if request.starts_with("GET /v6/invites/UNWEj54") {
+                    http_response(200, &[("Content-Type", "text/html")], INVITE.as_bytes())
+                } else {
+                    http_response(200, &[], b"<html><body>Discord is under maintenance</body></html>")
+                }
+            });
+            let client = Client::new().with_api_base(&server.url);
+            let result = client.fetch_invite("https://discord.gg/UNWEj54");
+            assert!(matches!(result, Err(Error::ServiceUnavailable)));
+            let result = client.fetch_invite("https://discord.gg/other");
+            assert!(matches!(result, Err(Error::ServiceUnavailable)));
+            assert!(matches!(client.fetch_template("hgM48av5Q69A"), Err(Error::ServiceUnavailable)));
+        }
+
+        #[test]
+        fn response_headers() {
+            let server = MockServer::start(|_| {
+                http_response(403, &[("Server", "cloudflare"), ("CF-Ray", "5f1b2c3d4e5f6a7b-CDG")], b"Forbidden")
+            });
+            let response = Client::new().request(&server.url, &[("Accept", "text/plain")]).unwrap();
+            assert_eq!(response.status_code, 403);
+            assert_eq!(response.headers["server"], "cloudflare");
+            assert_eq!(response.headers["cf-ray"], "5f1b2c3d4e5f6a7b-CDG");
+            assert_eq!(response.body_str(), Some("Forbidden"));
+            assert!(server.requests()[0].contains("\r\nAccept: text/plain\r\n"));
+        }
+
+        #[test]
+        fn default_headers() {
+            let server = MockServer::start(|_| http_response(200, &[], b""));
+            let client = Client::new().with_accept_language("fr-FR").with_cookie("CONSENT", "YES+");
+            client.request(&server.url, &[]).unwrap();
+            client.request(&server.url, &[("Accept", "text/plain"), ("Accept-Language", "de-DE")]).unwrap();
+
+            let requests = server.requests();
+            for request in &requests {
+                assert!(request.contains(&format!("\r\nUser-Agent: {}\r\n", USER_AGENT)));
+                assert!(request.contains("\r\nCookie: CONSENT=YES+\r\n"));
+            }
+            assert!(!requests[0].contains("Accept-Language"));
+            assert!(requests[1].contains("\r\nAccept-Language: de-DE\r\n"));
+            assert!(requests[1].contains("\r\nAccept: text/plain\r\n"));
+        }
+
+        #[test]
+        fn concurrent_fetches_share_rate_limit() {
+            let server = crate::mock::mock_discord(|code| code.to_string());
+            let client = Client::new().with_api_base(&server.url).with_rate_limit(40.0, 1);
+            let codes: Vec<String> = (0..12).map(|i| format!("code{}", i)).collect();
+
+            let start = Instant::now();
+            let invites = client.fetch_many_concurrent(&codes, 4);
+            // the first token is available at once, then one every 25 ms
+            assert!(start.elapsed() >= Duration::from_millis(270));
+            let fetched: Vec<String> = invites.into_iter().map(|invite| invite.unwrap().code).collect();
+            assert_eq!(fetched, codes);
+            assert_eq!(server.requests().len(), 12);
+
+            let results = client.fetch_many_concurrent(&["dead", "alive"], 8);
+            assert!(results[0].is_err());
+            assert_eq!(results[1].as_ref().unwrap().code, "alive");
+        }
+
+        #[test]
+        fn count_requests() {
+            let server = MockServer::start(|_| http_response(200, &[], INVITE.as_bytes()));
+            let client = Client::new().with_api_base(&server.url);
+            assert_eq!(client.request_count(), 0);
+            client.request(&server.url, &[]).unwrap();
+            client.fetch_invite("https://discord.gg/UNWEj54").unwrap();
+            client.resolve(&server.url).unwrap();
+            assert_eq!(client.request_count(), 3);
+            // invalid codes are rejected before any request
+            assert!(client.fetch_invite("https://discord.gg/a").is_err());
+            assert!(client.request("http://127.0.0.1:1/", &[]).is_err());
+            assert_eq!(client.request_count(), 4);
+        }
+
+        #[cfg(feature = "proxy")]
+        #[test]
+        fn proxies() {
+            let client = Client::new().with_proxy_env(&[("HTTPS_PROXY", "http://proxy.example:3128")]);
+            assert_eq!(client.proxy_for("https://discord.com/api").as_deref(), Some("http://proxy.example:3128"));
+            assert_eq!(client.proxy_for("http://example.com/"), None);
+
+            let client = Client::new().with_proxy_env(&[
+                ("http_proxy", "http://proxy.example:3128"),
+                ("ALL_PROXY", "http://all.example:3128"),
+                ("no_proxy", "localhost, .internal.example,discord.com"),
+            ]);
+            assert_eq!(client.proxy_for("http://example.com/").as_deref(), Some("http://proxy.example:3128"));
+            assert_eq!(client.proxy_for("https://example.com/").as_deref(), Some("http://all.example:3128"));
+            assert_eq!(client.proxy_for("https://discord.com/api"), None);
+            assert_eq!(client.proxy_for("https://cdn.discord.com/"), None);
+            assert_eq!(client.proxy_for("http://localhost:8080/"), None);
+            assert_eq!(client.proxy_for("http://api.internal.example/"), None);
+            assert_eq!(client.proxy_for("https://notdiscord.com/").as_deref(), Some("http://all.example:3128"));
+            let client = client.with_proxy_env(&[("NO_PROXY", "*"), ("ALL_PROXY", "http://all.example:3128")]);
+            assert_eq!(client.proxy_for("https://example.com/"), None);
+
+            let server = MockServer::start(|_| http_response(502, &[], b""));
+            let client = Client::new().with_proxy_env(&[]).with_proxy(&server.url);
+            assert_eq!(client.proxy_for("https://discord.com/api"), Some(server.url.clone()));
+
+            // minreq tunnels every request (and fails on the 502), reqwest forwards plain http ones
+            let _ = client.request("http://example.invalid/page", &[]);
+            let request = &server.requests()[0];
+            assert!(
+                request.starts_with("CONNECT example.invalid:80 HTTP/1.1\r\n")
+                    || request.starts_with("GET http://example.invalid/page HTTP/1.1\r\n")
+            );
+            let client = Client::new().with_proxy_env(&[]).with_proxy("socks5://127.0.0.1:1080");
+            assert!(matches!(client.request("http://example.invalid/", &[]), Err(Error::InvalidProxy)));
+        }
+
+        #[test]
+        fn domain_filters() {
+            let server = MockServer::start(|_| {
+                let results: Vec<String> = [
+                    "https://disboard.org/servers",
+                    "https://www.reddit.com/r/discordapp/",
+                    "https://spam.example.com/free-nitro",
+                    "https://example.com/servers",
+                    "https://notreddit.com/",
+                ]
+                .iter()
+                .map(|url| format!(r#"<div class="r"><a href="{}" onmousedown="return rwt(this)">x</a></div>"#, url))
+                .collect();
+                http_response(200, &[], results.concat().as_bytes())
+            });
+            assert_eq!(google::search_url(&Client::new(), &server.url).unwrap().len(), 5);
+
+            let client = Client::new().with_deny_domains(&["example.com"]);
+            assert_eq!(
+                google::search_url(&client, &server.url).unwrap(),
+                vec!["https://disboard.org/servers", "https://www.reddit.com/r/discordapp/", "https://notreddit.com/"]
+            );
+            let client = client.with_allow_domains(&["disboard.org", "reddit.com", "example.com"]);
+            assert_eq!(
+                google::search_url(&client, &server.url).unwrap(),
+                vec!["https://disboard.org/servers", "https://www.reddit.com/r/discordapp/"]
+            );
+        }
+
+        #[test]
+        fn fetch_progress() {
+            let server = crate::mock::mock_discord(|code| code.to_string());
+            let client = Client::new().with_api_base(&server.url);
+            let codes: Vec<String> = (0..10).map(|i| format!("code{}", i)).collect();
+            let mut calls = Vec::new();
+            let invites = client.fetch_many_concurrent_with_progress(&codes, 3, |done, total| calls.push((done, total)));
+            assert_eq!(invites.len(), 10);
+            assert_eq!(calls, (1..=10).map(|done| (done, 10)).collect::<Vec<_>>());
+        }
+
+        /// Answers as if every request had been redirected by `dis.gd`.
+        #[derive(Debug)]
+        struct ShortLinks;
+
+        impl HttpClient for ShortLinks {
+            fn get(&self, request: &HttpRequest) -> Result<RawResponse, Error> {
+                let url = match request.url {
+                    "https://dis.gd/rust" => "https://discord.gg/UNWEj54",
+                    "https://dis.gd/evil" => "https://example.com/discord.gg/UNWEj54",
+                    "https://dis.gd/safety" => "https://discord.com/safety",
+                    url => url,
+                };
+                Ok(RawResponse {
+                    status_code: 200,
+                    headers: HashMap::new(),
+                    url: url.to_string(),
+                    body: Vec::new(),
+                })
+            }
+        }
+
+        #[test]
+        fn short_links() {
+            let client = Client::new().with_http_client(ShortLinks);
+            assert_eq!(
+                client.resolve_short_link("https://dis.gd/rust").unwrap().as_deref(),
+                Some("https://discord.com/invite/UNWEj54")
+            );
+            assert_eq!(client.resolve_short_link("https://dis.gd/evil").unwrap(), None);
+            assert_eq!(client.resolve_short_link("https://dis.gd/safety").unwrap(), None);
+            // other links are not requested
+            assert_eq!(client.resolve_short_link("https://example.com/rust").unwrap(), None);
+            assert_eq!(client.request_count(), 3);
+            assert!(is_short_link("http://DIS.GD/rust"));
+            assert!(!is_short_link("https://notdis.gd/rust"));
+        }
+
+        #[test]
+        fn retry_budget() {
+            let server = MockServer::start(|request| {
+                if request.starts_with("GET /down ") {
+                    http_response(500, &[], b"")
+                } else {
+                    http_response(200, &[], b"")
+                }
+            });
+            let down = format!("{}/down", server.url);
+            let client = Client::new().with_retries(2, Duration::from_millis(1));
+            assert_eq!(client.request(&down, &[]).unwrap().status_code, 500);
+            assert_eq!(client.request_count(), 3);
+            assert_eq!(client.retries_left(), None);
+
+            let client = Client::new().with_retries(2, Duration::from_millis(1)).with_retry_budget(3);
+            assert_eq!(client.request(&server.url, &[]).unwrap().status_code, 200);
+            assert_eq!(client.retries_left(), Some(3));
+            client.request(&down, &[]).unwrap();
+            assert_eq!(client.request_count(), 4);
+            // only one retry left for this request, then none
+            client.request(&down, &[]).unwrap();
+            assert_eq!(client.request_count(), 6);
+            assert_eq!(client.retries_left(), Some(0));
+            client.request(&down, &[]).unwrap();
+            assert_eq!(client.request_count(), 7);
+            assert!(matches!(client.request("http://localhost:1/", &[]), Err(Error::Timeout)));
+            assert_eq!(client.request_count(), 8);
+        }
+
+        #[test]
+        fn dry_run() {
+            let server = crate::mock::mock_discord(|code| code.to_string());
+            let client = Client::new().with_api_base(&server.url).with_dry_run(true);
+            let query = SearchQueryBuilder::new().site("reddit.com");
+            assert!(client.search(&query).unwrap().is_empty());
+            assert!(client.resolve(&format!("{}/aaaa", server.url)).unwrap().is_empty());
+            let invite = client.fetch_invite("https://discord.gg/UNWEj54").unwrap();
+            assert_eq!(invite.code, "UNWEj54");
+            assert!(invite.guild.is_none());
+
+            assert!(server.requests().is_empty());
+            assert_eq!(client.request_count(), 0);
+            assert_eq!(
+                client.planned_requests(),
+                vec![
+                    query.build(),
+                    format!("{}/aaaa", server.url),
+                    format!("{}/v6/invites/UNWEj54?with_counts=true", server.url),
+                ]
+            );
+        }
+
+        #[test]
+        fn bot_token() {
+            let server = crate::mock::mock_discord(|code| code.to_string());
+            Client::new().with_api_base(&server.url).fetch_invite("https://discord.gg/aaaa").unwrap();
+            let client = Client::new().with_api_base(&server.url).with_bot_token("MTk4.secret");
+            client.fetch_invite("https://discord.gg/bbbb").unwrap();
+            client.resolve(&format!("{}/cccc", server.url)).unwrap();
+
+            let requests = server.requests();
+            assert!(!requests[0].contains("Authorization"));
+            assert!(requests[1].contains("\r\nAuthorization: Bot MTk4.secret\r\n"));
+            assert!(!requests[2].contains("Authorization"));
+            assert!(!format!("{:?}", client).contains("MTk4.secret"));
+        }
+
+        #[test]
+        fn circuit_breaker() {
+            let server = MockServer::start(|request| {
+                if request.starts_with("GET /down ") {
+                    http_response(503, &[], b"")
+                } else {
+                    http_response(200, &[], b"")
+                }
+            });
+            let down = format!("{}/down", server.url);
+            let client = Client::new().with_circuit_breaker(3, Duration::from_millis(100));
+            for _ in 0..3 {
+                assert_eq!(client.request(&down, &[]).unwrap().status_code, 503);
+            }
+            assert!(matches!(client.request(&down, &[]), Err(Error::CircuitOpen)));
+            assert!(matches!(client.request(&server.url, &[]), Err(Error::CircuitOpen)));
+            assert_eq!(server.requests().len(), 3);
+            // other hosts are not affected
+            assert!(matches!(client.request("http://localhost:1/", &[]), Err(Error::Timeout)));
+
+            // after the cooldown, one failure is enough to open the circuit again
+            sleep(Duration::from_millis(120));
+            assert_eq!(client.request(&down, &[]).unwrap().status_code, 503);
+            assert!(matches!(client.request(&server.url, &[]), Err(Error::CircuitOpen)));
+            sleep(Duration::from_millis(120));
+            assert_eq!(client.request(&server.url, &[]).unwrap().status_code, 200);
+            assert_eq!(client.request(&down, &[]).unwrap().status_code, 503);
+            assert_eq!(client.request(&server.url, &[]).unwrap().status_code, 200);
+            assert_eq!(server.requests().len(), 7);
+        }
+
+        #[test]
+        fn request_logging() {
+            struct CapturingLogger;
+            static RECORDS: Mutex<Vec<String>> = Mutex::new(Vec::new());
+            impl log::Log for CapturingLogger {
+                fn enabled(&self, _metadata: &log::Metadata) -> bool {
+                    true
+                }
+                fn log(&self, record: &log::Record) {
+                    RECORDS.lock().unwrap().push(format!("{} {}", record.level(), record.args()));
+                }
+                fn flush(&self) {}
+            }
+            static LOGGER: CapturingLogger = CapturingLogger;
+            log::set_logger(&LOGGER).unwrap();
+            log::set_max_level(log::LevelFilter::Trace);
+
+            let server = MockServer::start(|_| http_response(200, &[], b"hello"));
+            let url = format!("{}/logged", server.url);
+            Client::new().request(&url, &[]).unwrap();
+            let records = RECORDS.lock().unwrap();
+            assert!(records.contains(&format!("TRACE GET {}", url)));
+            assert!(records.contains(&format!("DEBUG GET {} -> 200 (5 bytes)", url)));
+        }
+
+        #[test]
+        fn max_body_bytes() {
+            let server = MockServer::start(|request| {
+                let body = format!("discord.gg/UNWEj54 {}", "a".repeat(1000));
+                if request.starts_with("GET /unannounced ") {
+                    // no Content-Length: the body is read until the connection is closed
+                    let mut response = b"HTTP/1.1 200 OK\r\nConnection: close\r\n\r\n".to_vec();
+                    response.extend_from_slice(body.as_bytes());
+                    response
+                } else {
+                    http_response(200, &[], body.as_bytes())
+                }
+            });
+            let client = Client::new().with_max_body_bytes(1019);
+            assert_eq!(client.request(&server.url, &[]).unwrap().body.len(), 1019);
+            assert_eq!(client.resolve(&server.url).unwrap(), vec!["https://discord.com/invite/UNWEj54"]);
+
+            let client = Client::new().with_max_body_bytes(100);
+            assert!(matches!(client.request(&server.url, &[]), Err(Error::ResponseTooLarge)));
+            assert!(matches!(client.resolve(&server.url), Err(Error::ResponseTooLarge)));
+            let unannounced = format!("{}/unannounced", server.url);
+            assert!(matches!(client.request(&unannounced, &[]), Err(Error::ResponseTooLarge)));
+            assert!(matches!(google::search_url(&client, &server.url), Err(Error::ResponseTooLarge)));
+            let client = client.with_api_base(&server.url);
+            assert!(matches!(client.fetch_invite("https://discord.gg/UNWEj54"), Err(Error::ResponseTooLarge)));
+        }
+
+        #[test]
+        fn cookie_jar() {
+            let server = MockServer::start(|request| {
+                if request.starts_with("GET /first ") {
+                    let set_cookie = "NID=511=abc; expires=Sat, 01-Jan-2050 00:00:00 GMT; path=/; HttpOnly";
+                    http_response(200, &[("Set-Cookie", set_cookie)], b"")
+                } else {
+                    http_response(200, &[("Set-Cookie", "CONSENT=; Max-Age=0")], b"")
+                }
+            });
+            let client = Client::new().with_cookie("CONSENT", "YES+");
+            client.request(&format!("{}/first", server.url), &[]).unwrap();
+            assert_eq!(client.cookie("NID").as_deref(), Some("511=abc"));
+            client.request(&format!("{}/second", server.url), &[]).unwrap();
+            assert_eq!(client.cookie("CONSENT"), None);
+            client.request(&format!("{}/third", server.url), &[]).unwrap();
+
+            let requests = server.requests();
+            assert!(requests[0].contains("\r\nCookie: CONSENT=YES+\r\n"));
+            assert!(requests[1].contains("\r\nCookie: CONSENT=YES+; NID=511=abc\r\n"));
+            assert!(requests[2].contains("\r\nCookie: NID=511=abc\r\n"));
+        }
+
+        #[test]
+        fn rate_limit_buckets() {
+            let headers = |pairs: &[(&str, &str)]| -> HashMap<String, String> {
+                pairs.iter().map(|(name, value)| (name.to_string(), value.to_string())).collect()
+            };
+            let mut buckets = Buckets::default();
+            assert_eq!(buckets.delay("invites"), None);
+            buckets.update(
+                "invites",
+                &headers(&[("x-ratelimit-bucket", "841722"), ("x-ratelimit-remaining", "1"), ("x-ratelimit-reset-after", "5")]),
+            );
+            assert_eq!(buckets.delay("invites"), None);
+
+            // the templates share the bucket of the invites
+            let reset_after = buckets.update(
+                "templates",
+                &headers(&[("x-ratelimit-bucket", "841722"), ("x-ratelimit-remaining", "0"), ("x-ratelimit-reset-after", "5.5")]),
+            );
+            assert_eq!(reset_after, Some(Duration::from_millis(5500)));
+            let delay = buckets.delay("invites").unwrap();
+            assert!(delay > Duration::from_secs(5) && delay <= Duration::from_millis(5500));
+            assert_eq!(buckets.delay("widget"), None);
+
+            // a bucket resets on its own, and responses without headers are ignored
+            buckets.update("widget", &headers(&[("x-ratelimit-remaining", "0"), ("x-ratelimit-reset-after", "0")]));
+            assert_eq!(buckets.delay("widget"), None);
+            buckets.update("invites", &headers(&[]));
+            assert!(buckets.delay("templates").is_some());
+        }
+
+        #[test]
+        fn wait_for_exhausted_bucket() {
+            let count = AtomicUsize::new(0);
+            let server = MockServer::start(move |_| {
+                let remaining = if count.fetch_add(1, Ordering::Relaxed) == 0 { "0" } else { "4" };
+                http_response(
+                    200,
+                    &[
+                        ("X-RateLimit-Bucket", "abcd1234"),
+                        ("X-RateLimit-Remaining", remaining),
+                        ("X-RateLimit-Reset-After", "0.2"),
+                    ],
+                    INVITE.as_bytes(),
+                )
+            });
+            let client = Client::new().with_api_base(&server.url);
+            let start = Instant::now();
+            client.fetch_invite("https://discord.gg/UNWEj54").unwrap();
+            assert!(start.elapsed() < Duration::from_millis(150));
+            client.fetch_invite("https://discord.gg/UNWEj54").unwrap();
+            assert!(start.elapsed() >= Duration::from_millis(200));
+            let start = Instant::now();
+            client.fetch_invite("https://discord.gg/UNWEj54").unwrap();
+            assert!(start.elapsed() < Duration::from_millis(150));
+
+            let server = MockServer::start(|_| {
+                http_response(
+                    429,
+                    &[("X-RateLimit-Global", "true"), ("X-RateLimit-Reset-After", "3")],
+                    br#"{"retry_after": 3000, "global": true}"#,
+                )
+            });
+            let client = Client::new().with_api_base(&server.url);
+            assert!(matches!(client.fetch_template("hgM48av5Q69A"), Err(Error::RateLimited(_))));
+            assert!(client.global_cooldown().unwrap() > Duration::from_secs(2));
+        }
+
+        #[test]
+        fn global_rate_limit() {
+            let client = Client::new();
+            assert!(client.global_cooldown().is_none());
+
+            let error = client.handle_rate_limit(r#"{"message": "You are being rate limited.", "retry_after": 6457, "global": false}"#);
+            assert!(matches!(error, Error::RateLimited(d) if d == Duration::from_millis(6457)));
+            assert!(client.global_cooldown().is_none());
+
+            let error = client.handle_rate_limit(r#"{"message": "You are being rate limited.", "retry_after": 5000, "global": true}"#);
+            assert!(matches!(error, Error::RateLimited(d) if d == Duration::from_secs(5)));
+            let cooldown = client.global_cooldown().unwrap();
+            assert!(cooldown > Duration::from_secs(4) && cooldown <= Duration::from_secs(5));
+
+            // a shorter global limit does not shorten the current cooldown
+            client.handle_rate_limit(r#"{"retry_after": 10, "global": true}"#);
+            assert!(client.global_cooldown().unwrap() > Duration::from_secs(4));
+        }
+    }
+}
+
+/// Contains a cache of fetched invites.
+pub mod cache {
+    use super::client::Client;
+    use super::discord::Invite;
+    use super::Error;
+    use std::collections::{HashMap, HashSet};
+    use std::sync::{Arc, Condvar, Mutex};
+    use std::time::{Duration, Instant};
+
+    /// Keeps the last fetched version of each invite, indexed by code and by guild id.
+    #[derive(Debug, Default)]
+    pub struct InviteCache {
+        client: Client,
+        invites: HashMap<String, Invite>,
+        /// guild id -> invite code
+        guilds: HashMap<String, String>,
+        /// invite code -> ETag of the stored version
+        etags: HashMap<String, String>,
+        /// invite code -> when the stored version was fetched or inserted
+        updated_at: HashMap<String, Instant>,
+    }
+
+    impl InviteCache {
+        /// Create an empty cache fetching invites with `client`.
+        pub fn new(client: Client) -> InviteCache {
+            InviteCache {
+                client,
+                invites: HashMap::new(),
+                guilds: HashMap::new(),
+                etags: HashMap::new(),
+                updated_at: HashMap::new(),
+            }
+        }
+
+        /// Store an invite, replacing the previous version with the same code.
+        pub fn insert(&mut self, invite: Invite) {
+            self.etags.remove(&invite.code);
+            self.updated_at.insert(invite.code.clone(), Instant::now());
+            if let Some(guild) = &invite.guild {
+                self.guilds.insert(guild.id().to_string(), invite.code.clone());
+            }
+            self.invites.insert(invite.code.clone(), invite);
+        }
+
+        pub fn get(&self, code: &str) -> Option<&Invite> {
+            self.invites.get(code)
+        }
+
+        /// Return the code stored for a guild.
+        pub fn code_for_guild(&self, guild_id: &str) -> Option<&str> {
+            self.guilds.get(guild_id).map(String::as_str)
+        }
+
+        /// Fetch an invite. If a version with a known ETag is stored, it is only downloaded again if it changed.
+        fn fetch_code(&mut self, code: &str) -> Result<Invite, Error> {
+            let url = format!("https://discord.gg/{}", code);
+            let etag = self.etags.get(code).map(String::as_str);
+            let (invite, etag) = match self.client.fetch_invite_if_modified(&url, etag)? {
+                Some(modified) => modified,
+                None => match self.invites.get(code) {
+                    Some(cached) => {
+                        self.updated_at.insert(code.to_string(), Instant::now());
+                        return Ok(cached.clone());
+                    }
+                    None => return Err(Error::NotCached),
+                },
+            };
+            self.insert(invite.clone());
+            if let Some(etag) = etag {
+                self.etags.insert(code.to_string(), etag);
+            }
+            Ok(invite)
+        }
+
+        /// Return the stored invite if it was fetched less than `max_age` ago, otherwise fetch it again
+        /// (conditionally, as [`InviteCache::refresh`]). Each call can use the age fitting the fields it needs.
+        pub fn get_fresh(&mut self, code: &str, max_age: Duration) -> Result<Invite, Error> {
+            let fresh = self.updated_at.get(code).is_some_and(|updated_at| updated_at.elapsed() <= max_age);
+            match self.invites.get(code) {
+                Some(invite) if fresh => Ok(invite.clone()),
+                _ => self.fetch_code(code),
+            }
+        }
+
+        /// Forget the stored version of an invite, so that the next [`InviteCache::get_fresh`] downloads it
+        /// unconditionally.
+        pub fn invalidate(&mut self, code: &str) {
+            if let Some(invite) = self.invites.remove(code) {
+                if let Some(guild) = &invite.guild {
+                    if self.guilds.get(guild.id()).map(String::as_str) == Some(code) {
+                        self.guilds.remove(guild.id());
+                    }
+                }
+            }
+            self.etags.remove(code);
+            self.updated_at.remove(code);
+        }
+
+        /// Fetch again the invite stored for a guild, without having to resolve it again.  
+        /// The request is conditional when the stored version has an ETag: if it did not change,
+        /// the stored invite is returned.  
+        /// Returns [`Error::NotCached`] if no invite of this guild was stored.
+        pub fn refresh(&mut self, guild_id: &str) -> Result<Invite, Error> {
+            let code = match self.guilds.get(guild_id) {
+                Some(code) => code.clone(),
+                None => return Err(Error::NotCached),
+            };
+            self.fetch_code(&code)
+        }
+    }
+
+    #[derive(Debug)]
+    struct Shared {
+        client: Client,
+        cache: Mutex<SharedState>,
+        /// Notified when a fetch completes
+        fetched: Condvar,
+    }
+
+    #[derive(Debug, Default)]
+    struct SharedState {
+        invites: InviteCache,
+        /// codes being fetched by a thread
+        pending: HashSet<String>,
+    }
+
+    /// An invite cache shared by the threads of a concurrent crawler. Clones share the same entries.  
+    /// When several threads need the same invite at once, only one of them fetches it and the others wait for it.
+    #[derive(Debug, Clone)]
+    pub struct SharedInviteCache {
+        shared: Arc<Shared>,
+    }
+
+    impl SharedInviteCache {
+        /// Create an empty cache fetching invites with `client`.
+        pub fn new(client: Client) -> SharedInviteCache {
+            SharedInviteCache {
+                shared: Arc::new(Shared {
+                    client,
+                    cache: Mutex::new(SharedState::default()),
+                    fetched: Condvar::new(),
+                }),
+            }
+        }
+
+        /// Store an invite, replacing the previous version with the same code.
+        pub fn insert(&self, invite: Invite) {
+            self.shared.cache.lock().unwrap().invites.insert(invite);
+        }
+
+        pub fn get(&self, code: &str) -> Option<Invite> {
+            self.shared.cache.lock().unwrap().invites.get(code).cloned()
+        }
+
+        /// Return the code stored for a guild.
+        pub fn code_for_guild(&self, guild_id: &str) -> Option<String> {
+            self.shared.cache.lock().unwrap().invites.code_for_guild(guild_id).map(str::to_string)
+        }
+
+        /// Return the stored invite, or fetch it if it is not stored yet.  
+        /// If another thread is already fetching it, wait for its result instead of sending another request.
+        /// Failed fetches are not stored.
+        pub fn get_or_fetch(&self, code: &str) -> Result<Invite, Error> {
+            let mut state = self.shared.cache.lock().unwrap();
+            loop {
+                if let Some(invite) = state.invites.get(code) {
+                    return Ok(invite.clone());
+                }
+                if !state.pending.contains(code) {
+                    break;
+                }
+                state = self.shared.fetched.wait(state).unwrap();
+            }
+            state.pending.insert(code.to_string());
+            drop(state);
+
+            let result = self.shared.client.fetch_invite(&format!("https://discord.gg/{}", code));
+            let mut state = self.shared.cache.lock().unwrap();
+            state.pending.remove(code);
+            if let Ok(invite) = &result {
+                state.invites.insert(invite.clone());
+            }
+            self.shared.fetched.notify_all();
+            result
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use crate::mock::{http_response, MockServer};
+
+        #[test]
+        fn refresh_by_guild_id() {
+            let server = MockServer::start(|_| {
+                http_response(
+                    200,
+                    &[],
+                    br#"{
+                        "code": "UNWEj54",
+                        "guild": {"id": "81384788765712384", "name": "Server", "verification_level": 0},
+                        "approximate_member_count": 20,
+                        "approximate_presence_count": 5
+                    }"#,
+                )
+            });
+            let mut cache = InviteCache::new(Client::new().with_api_base(&server.url));
+            cache.insert(
+                serde_json::from_str(
+                    r#"{
+                        "code": "UNWEj54",
+                        "guild": {"id": "81384788765712384", "name": "Server", "verification_level": 0},
+                        "approximate_member_count": 10,
+                        "approximate_presence_count": 2
+                    }"#,
+                )
+                .unwrap(),
+            );
+            assert_eq!(cache.code_for_guild("81384788765712384"), Some("UNWEj54"));
+
+            let invite = cache.refresh("81384788765712384").unwrap();
+            assert_eq!(invite.approximate_member_count, Some(20));
+            assert_eq!(cache.get("UNWEj54").unwrap().approximate_member_count, Some(20));
+            assert!(server.requests()[0].starts_with("GET /v6/invites/UNWEj54?"));
+
+            assert!(matches!(cache.refresh("0"), Err(Error::NotCached)));
+            assert_eq!(server.requests().len(), 1);
+        }
+
+        #[test]
+        fn refresh_not_modified() {
+            let server = MockServer::start(|request| {
+                if request.contains("\r\nIf-None-Match: \"v1\"\r\n") {
+                    http_response(304, &[("ETag", "\"v1\"")], b"")
+                } else {
+                    http_response(
+                        200,
+                        &[("ETag", "\"v1\"")],
+                        br#"{
+                            "code": "UNWEj54",
+                            "guild": {"id": "1", "name": "Server", "verification_level": 0},
+                            "approximate_member_count": 10,
+                            "approximate_presence_count": 2
+                        }"#,
+                    )
+                }
+            });
+            let client = Client::new().with_api_base(&server.url);
+            let (invite, etag) = client
+                .fetch_invite_if_modified("https://discord.gg/UNWEj54", None)
+                .unwrap()
+                .unwrap();
+            assert_eq!(etag.as_deref(), Some("\"v1\""));
+            assert!(client
+                .fetch_invite_if_modified("https://discord.gg/UNWEj54", Some("\"v1\""))
+                .unwrap()
+                .is_none());
+
+            let mut cache = InviteCache::new(client);
+            cache.insert(invite);
+            // no ETag is known for the inserted invite: the first refresh downloads it
+            assert_eq!(cache.refresh("1").unwrap().approximate_member_count, Some(10));
+            assert_eq!(cache.refresh("1").unwrap().approximate_member_count, Some(10));
+
+            let requests = server.requests();
+            assert_eq!(requests.len(), 4);
+            assert!(!requests[2].contains("If-None-Match"));
+            assert!(requests[3].contains("\r\nIf-None-Match: \"v1\"\r\n"));
+        }
+
+        #[test]
+        fn fresh_entries() {
+            let server = crate::mock::mock_discord(|code| format!("guild-{}", code));
+            let mut cache = InviteCache::new(Client::new().with_api_base(&server.url));
+            let hour = Duration::from_secs(3600);
+            assert_eq!(cache.get_fresh("aaaa", hour).unwrap().code, "aaaa");
+            cache.get_fresh("aaaa", hour).unwrap();
+            assert_eq!(server.requests().len(), 1);
+
+            std::thread::sleep(Duration::from_millis(30));
+            cache.get_fresh("aaaa", Duration::from_millis(10)).unwrap();
+            assert_eq!(server.requests().len(), 2);
+            cache.get_fresh("aaaa", hour).unwrap();
+            assert_eq!(server.requests().len(), 2);
+
+            cache.invalidate("aaaa");
+            assert!(cache.get("aaaa").is_none());
+            assert!(cache.code_for_guild("guild-aaaa").is_none());
+            cache.get_fresh("aaaa", hour).unwrap();
+            assert_eq!(server.requests().len(), 3);
+            assert!(!server.requests()[2].contains("If-None-Match"));
+            cache.invalidate("unknown");
+        }
+
+        #[test]
+        fn shared_between_threads() {
+            let server = MockServer::start(|request| {
+                std::thread::sleep(std::time::Duration::from_millis(50));
+                if request.starts_with("GET /v6/invites/dead") {
+                    http_response(404, &[], b"")
+                } else {
+                    http_response(
+                        200,
+                        &[],
+                        br#"{"code": "UNWEj54", "guild": {"id": "1", "name": "Server", "verification_level": 0}}"#,
+                    )
+                }
+            });
+            let cache = SharedInviteCache::new(Client::new().with_api_base(&server.url));
+            std::thread::scope(|scope| {
+                for _ in 0..8 {
+                    let cache = cache.clone();
+                    scope.spawn(move || assert_eq!(cache.get_or_fetch("UNWEj54").unwrap().code, "UNWEj54"));
+                }
+            });
+            assert_eq!(server.requests().len(), 1);
+            assert_eq!(cache.code_for_guild("1").as_deref(), Some("UNWEj54"));
+            assert!(cache.get("UNWEj54").is_some());
+
+            assert!(cache.get_or_fetch("dead").is_err());
+            assert!(cache.get("dead").is_none());
+            assert_eq!(server.requests().len(), 2);
+        }
+    }
+}
+
+/// Contains the Crawler, which chains google searches, page resolutions and invite fetches.
+pub mod crawler {
+    use super::client::Client;
+    use super::discord::{get_invite_code, Invite};
+    use super::intermediary::looks_promising;
+    use super::util::normalize_source_url;
+    use super::google::{self, SearchQueryBuilder};
+    use super::Error;
+    use log::debug;
+    use serde::{Deserialize, Serialize};
+    use std::collections::HashSet;
+    use std::fs::{File, OpenOptions};
+    use std::io::{self, BufRead, BufReader, Read, Seek, SeekFrom, Write};
+    use std::path::Path;
+    use std::thread::sleep;
+    use std::time::{Duration, Instant, SystemTime};
+
+    const DEFAULT_DELAY: Duration = Duration::from_secs(1);
+
+    type SearchFn = Box<dyn FnMut(usize) -> Result<Vec<String>, Error>>;
+    type ProgressFn = Box<dyn FnMut(usize, usize)>;
+    type KeyFn = Box<dyn Fn(&Invite) -> String>;
+
+    /// An invite found by a [`Crawler`], with discovery metadata.
+    #[derive(Debug, Clone)]
+    pub struct DiscoveredInvite {
+        pub invite: Invite,
+        /// When the invite was fetched.
+        pub discovered_at: SystemTime,
+        /// The page where the invite was found.
+        pub source_url: String,
+    }
+
+    /// A destination for the invites discovered by a [`Crawler`] (a database, a file, a channel...).
+    pub trait InviteSink {
+        fn accept(&mut self, invite: Invite);
+
+        /// Receive an invite with its discovery metadata. Discards the metadata by default.
+        fn accept_discovered(&mut self, discovered: DiscoveredInvite) {
+            self.accept(discovered.invite);
+        }
+    }
+
+    impl InviteSink for Vec<DiscoveredInvite> {
+        fn accept(&mut self, invite: Invite) {
+            self.push(DiscoveredInvite {
+                invite,
+                discovered_at: SystemTime::now(),
+                source_url: String::new(),
+            });
+        }
+
+        fn accept_discovered(&mut self, discovered: DiscoveredInvite) {
+            self.push(discovered);
+        }
+    }
+
+    /// A sink collecting the invites in a Vec.
+    #[derive(Debug, Default)]
+    pub struct VecSink {
+        pub invites: Vec<Invite>,
+    }
+
+    impl InviteSink for VecSink {
+        fn accept(&mut self, invite: Invite) {
+            self.invites.push(invite);
+        }
+    }
+
+    /// Load the codes of the invites of a JSON Lines file (one serialized [`Invite`] per line), for example
+    /// to give them to [`Crawler::with_seen`].  
+    /// A missing file has no codes, and the lines that are not invites (such as a line truncated by a crash)
+    /// are ignored.
+    pub fn load_seen_codes<P: AsRef<Path>>(path: P) -> io::Result<HashSet<String>> {
+        let file = match File::open(path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(HashSet::new()),
+            Err(e) => return Err(e),
+        };
+        let mut codes = HashSet::new();
+        for line in BufReader::new(file).lines() {
+            match serde_json::from_str::<Invite>(&line?) {
+                Ok(invite) => {
+                    codes.insert(invite.code);
+                }
+                Err(e) => debug!("ignoring an invalid line: {}", e),
+            }
+        }
+        Ok(codes)
+    }
+
+    /// Append to a JSON Lines file the invites it does not contain yet, and return how many were appended.  
+    /// The file is created if needed. This keeps the output of periodic crawls free of duplicates.
+    pub fn append_new_invites<P: AsRef<Path>>(path: P, invites: &[DiscoveredInvite]) -> io::Result<usize> {
+        let mut seen = load_seen_codes(&path)?;
+        let mut file = OpenOptions::new().read(true).create(true).append(true).open(path)?;
+        // do not continue a line truncated by a crash
+        if file.metadata()?.len() > 0 {
+            let mut last = [0];
+            file.seek(SeekFrom::End(-1))?;
+            file.read_exact(&mut last)?;
+            if last[0] != b'\n' {
+                writeln!(file)?;
+            }
+        }
+        let mut appended = 0;
+        for found in invites {
+            if seen.insert(found.invite.code.clone()) {
+                let line = serde_json::to_string(&found.invite).map_err(io::Error::other)?;
+                writeln!(file, "{}", line)?;
+                appended += 1;
+            }
+        }
+        Ok(appended)
+    }
+
+    /// The progress of a [`Crawler`], to persist between runs (see [`Crawler::checkpoint`]).
+    #[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+    pub struct CrawlState {
+        /// The next page to load.
+        pub page: usize,
+        pub seen_codes: HashSet<String>,
+        pub seen_guilds: HashSet<String>,
+        /// The deduplication keys of the invites returned (see [`Crawler::with_dedup_key`]).
+        #[serde(default)]
+        pub seen_keys: HashSet<String>,
+    }
+
+    /// What to do when a result page cannot be resolved or an invite cannot be fetched.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum ErrorPolicy {
+        /// Skip the failing item and continue. Nothing is lost, but a broken network or a ban
+        /// keeps consuming requests until the end of the crawl.
+        ContinueAll,
+        /// Return the first error. This saves time and quota, but the invites of the current page are lost
+        /// and dead invites (a common case) end the crawl too.
+        AbortOnError,
+    }
+
+    /// Crawls google result pages one after the other and fetches every new invite found in the results.  
+    /// Invites are only fetched once per crawler, even if they are found again on later pages,
+    /// and only the first invite of each guild is returned (see [`Crawler::with_dedup_key`]).
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use discord_finder::{client::Client, crawler::Crawler};
+    ///
+    /// // stop when less than 20% of the invites of 2 consecutive pages are new
+    /// let mut crawler = Crawler::new(Client::new()).with_min_yield(0.2, 2);
+    /// for found in crawler.crawl(10).unwrap() {
+    ///     println!("{}", found.invite.to_markdown());
+    /// }
+    /// ```
+    pub struct Crawler {
+        client: Client,
+        /// `None` searches the default query with `client`
+        search: Option<SearchFn>,
+        delay: Duration,
+        last_fetch: Option<Instant>,
+        next_page: usize,
+        seen_codes: HashSet<String>,
+        seen_guilds: HashSet<String>,
+        dedup_key: KeyFn,
+        seen_keys: HashSet<String>,
+        total_invites_found: usize,
+        /// invite codes and guild ids
+        blacklist: HashSet<String>,
+        /// (threshold, consecutive pages)
+        min_yield: Option<(f64, usize)>,
+        low_yield_pages: usize,
+        error_policy: ErrorPolicy,
+        progress: Option<ProgressFn>,
+    }
+
+    impl Crawler {
+        /// Create a crawler searching and fetching invites with `client`. The pages of the default query
+        /// (see [`google::search`]) are searched with [`Client::search`], so that the settings of the client
+        /// (domain filters, proxy, dry run...) also apply to the searches.
+        pub fn new(client: Client) -> Crawler {
+            Crawler {
+                client,
+                search: None,
+                delay: DEFAULT_DELAY,
+                last_fetch: None,
+                next_page: 0,
+                seen_codes: HashSet::new(),
+                seen_guilds: HashSet::new(),
+                dedup_key: Box::new(Invite::dedup_key),
+                seen_keys: HashSet::new(),
+                total_invites_found: 0,
+                blacklist: HashSet::new(),
+                min_yield: None,
+                low_yield_pages: 0,
+                error_policy: ErrorPolicy::ContinueAll,
+                progress: None,
+            }
+        }
+
+        /// Replace the function producing the result urls of a page (default is the default query searched
+        /// with the client of the crawler, see [`Crawler::new`]).
+        pub fn with_search<F>(mut self, search: F) -> Crawler
+        where
+            F: FnMut(usize) -> Result<Vec<String>, Error> + 'static,
+        {
+            self.search = Some(Box::new(search));
+            self
+        }
+
+        /// Set the minimum delay between two invite fetches (default is 1 second).
+        pub fn with_delay(mut self, delay: Duration) -> Crawler {
+            self.delay = delay;
+            self
+        }
+
+        /// Never return the invites whose code or guild id is in `blacklist`.  
+        /// Blacklisted codes are not even fetched, blacklisted guilds are dropped after the fetch.
+        pub fn with_blacklist(mut self, blacklist: HashSet<String>) -> Crawler {
+            self.blacklist = blacklist;
+            self
+        }
+
+        /// Consider the codes of `seen` as already fetched, so that they are skipped.  
+        /// Pass the [`Crawler::seen_codes`] of a previous run to resume it.
+        pub fn with_seen(mut self, seen: HashSet<String>) -> Crawler {
+            self.seen_codes = seen;
+            self
+        }
+
+        /// Stop crawling once the ratio of new invites among the invites found on a page stays below `threshold`
+        /// for `consecutive_pages` pages in a row. Deep google pages mostly contain duplicates.
+        pub fn with_min_yield(mut self, threshold: f64, consecutive_pages: usize) -> Crawler {
+            self.min_yield = Some((threshold, consecutive_pages.max(1)));
+            self
+        }
+
+        /// Set what to do on errors (default is [`ErrorPolicy::ContinueAll`]).  
+        /// Search errors always end the crawl.
+        pub fn with_error_policy(mut self, error_policy: ErrorPolicy) -> Crawler {
+            self.error_policy = error_policy;
+            self
+        }
+
+        /// Only return the first invite of each key computed by `dedup_key`. The default key is
+        /// [`Invite::dedup_key`]: the guild id, or the code of the invites without guild.
+        pub fn with_dedup_key<F>(mut self, dedup_key: F) -> Crawler
+        where
+            F: Fn(&Invite) -> String + 'static,
+        {
+            self.dedup_key = Box::new(dedup_key);
+            self
+        }
+
+        /// Call `progress(done, total)` after each page crawled by [`Crawler::crawl`] (or [`Crawler::crawl_into`]),
+        /// where `total` is the maximum number of pages. The crawl can end before `done` reaches `total`.
+        pub fn with_progress<F>(mut self, progress: F) -> Crawler
+        where
+            F: FnMut(usize, usize) + 'static,
+        {
+            self.progress = Some(Box::new(progress));
+            self
+        }
+
+        /// Continue a crawl from a state returned by [`Crawler::checkpoint`]: the next page loaded is the one
+        /// following the checkpoint, and the invites and guilds found before are skipped.
+        pub fn resume(mut self, state: CrawlState) -> Crawler {
+            self.next_page = state.page;
+            self.seen_codes = state.seen_codes;
+            self.seen_guilds = state.seen_guilds;
+            self.seen_keys = state.seen_keys;
+            self
+        }
+
+        /// Capture the progress of the crawl, so that it can be resumed after a restart with [`Crawler::resume`].
+        pub fn checkpoint(&self) -> CrawlState {
+            CrawlState {
+                page: self.next_page,
+                seen_codes: self.seen_codes.clone(),
+                seen_guilds: self.seen_guilds.clone(),
+                seen_keys: self.seen_keys.clone(),
+            }
+        }
+
+        /// The page the next call to [`Crawler::crawl_page`] will load.
+        pub fn next_page(&self) -> usize {
+            self.next_page
+        }
+
+        /// The invite codes found so far (and the ones given to [`Crawler::with_seen`]), to persist across runs.
+        pub fn seen_codes(&self) -> &HashSet<String> {
+            &self.seen_codes
+        }
+
+        /// The number of distinct guilds discovered so far.
+        pub fn unique_guild_count(&self) -> usize {
+            self.seen_guilds.len()
+        }
+
+        /// The number of invites fetched so far, including the ones leading to an already discovered guild.
+        pub fn total_invites_found(&self) -> usize {
+            self.total_invites_found
+        }
+
+        /// Return true if the yield of the last pages was too low to continue.
+        pub fn is_exhausted(&self) -> bool {
+            match self.min_yield {
+                Some((_, consecutive_pages)) => self.low_yield_pages >= consecutive_pages,
+                None => false,
+            }
+        }
+
+        fn wait_delay(&mut self) {
+            if let Some(last_fetch) = self.last_fetch {
+                if let Some(remaining) = self.delay.checked_sub(last_fetch.elapsed()) {
+                    sleep(remaining);
+                }
+            }
+            self.last_fetch = Some(Instant::now());
+        }
+
+        /// Crawl the next page and return the invites that were not found before.  
+        /// Result pages that cannot be loaded and invites that cannot be fetched are handled according to the
+        /// [`ErrorPolicy`].
+        pub fn crawl_page(&mut self) -> Result<Vec<DiscoveredInvite>, Error> {
+            let mut invites = Vec::new();
+            self.crawl_page_into(&mut invites)?;
+            Ok(invites)
+        }
+
+        /// Same as [`Crawler::crawl_page`] but gives each new invite to `sink` as soon as it is fetched
+        /// (see [`InviteSink::accept_discovered`]).
+        pub fn crawl_page_into<S: InviteSink>(&mut self, sink: &mut S) -> Result<(), Error> {
+            let mut links = match &mut self.search {
+                Some(search) => search(self.next_page)?,
+                None => self.client.search(&SearchQueryBuilder::new().page(self.next_page))?,
+            };
+            self.next_page += 1;
+            // the most promising pages first, keeping the order of google otherwise
+            links.sort_by_key(|link| !looks_promising(link));
+
+            // a page linked twice (with and without `www.`, a trailing slash...) is only resolved once
+            let mut sources = HashSet::new();
+            links.retain(|link| sources.insert(normalize_source_url(link)));
+
+            let mut found = 0;
+            let mut new_links = Vec::new();
+            for link in links {
+                let invite_links = match self.client.resolve(&link) {
+                    Ok(invite_links) => invite_links,
+                    Err(e) if self.error_policy == ErrorPolicy::AbortOnError => return Err(e),
+                    Err(_) => continue,
+                };
+                for invite_link in invite_links {
+                    found += 1;
+                    if let Some(code) = get_invite_code(&invite_link) {
+                        if self.seen_codes.insert(code.to_string()) && !self.blacklist.contains(code) {
+                            new_links.push((invite_link, link.clone()));
+                        }
+                    }
+                }
+            }
+
+            debug!("page {}: {} invites found, {} new", self.next_page - 1, found, new_links.len());
+            if let Some((threshold, _)) = self.min_yield {
+                let ratio = if found == 0 { 0.0 } else { new_links.len() as f64 / found as f64 };
+                if ratio < threshold {
+                    self.low_yield_pages += 1;
+                } else {
+                    self.low_yield_pages = 0;
+                }
+            }
+
+            for (invite_link, source_url) in new_links {
+                self.wait_delay();
+                let invite = match self.client.fetch_invite(&invite_link) {
+                    Ok(invite) => invite,
+                    Err(e) if self.error_policy == ErrorPolicy::AbortOnError => return Err(e),
+                    Err(_) => continue,
+                };
+                if let Some(guild) = &invite.guild {
+                    if self.blacklist.contains(guild.id()) {
+                        continue;
+                    }
+                    self.seen_guilds.insert(guild.id().to_string());
+                }
+                self.total_invites_found += 1;
+                if self.seen_keys.insert((self.dedup_key)(&invite)) {
+                    sink.accept_discovered(DiscoveredInvite {
+                        invite,
+                        discovered_at: SystemTime::now(),
+                        source_url,
+                    });
+                }
+            }
+            Ok(())
+        }
+
+        /// Crawl up to `max_pages` pages, stopping earlier if the yield becomes too low
+        /// (see [`Crawler::with_min_yield`]).
+        pub fn crawl(&mut self, max_pages: usize) -> Result<Vec<DiscoveredInvite>, Error> {
+            let mut invites = Vec::new();
+            self.crawl_into(max_pages, &mut invites)?;
+            Ok(invites)
+        }
+
+        /// Estimate the number of requests of a crawl of `pages` pages, to plan it against rate limits:
+        /// one search per page, one request per result ([`google::DEFAULT_RESULTS_PER_PAGE`] results per page)
+        /// and one fetch per invite found on the page.  
+        /// This is an upper bound, since invites found several times are only fetched once.
+        pub fn estimate_requests(pages: usize, avg_invites_per_page: f64) -> u64 {
+            let per_page = 1 + google::DEFAULT_RESULTS_PER_PAGE as u64;
+            let fetches = (pages as f64 * avg_invites_per_page.max(0.0)).ceil() as u64;
+            pages as u64 * per_page + fetches
+        }
+
+        /// Same as [`Crawler::crawl`] but gives each new invite to `sink` as soon as it is fetched,
+        /// so that long crawls can store their results progressively.
+        pub fn crawl_into<S: InviteSink>(&mut self, max_pages: usize, sink: &mut S) -> Result<(), Error> {
+            for done in 1..=max_pages {
+                if self.is_exhausted() {
+                    break;
+                }
+                self.crawl_page_into(sink)?;
+                if let Some(progress) = &mut self.progress {
+                    progress(done, max_pages);
+                }
+            }
+            Ok(())
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use crate::backend::{HttpClient, HttpRequest};
+        use crate::client::RawResponse;
+        use crate::mock::{http_response, mock_discord, mock_search, MockServer};
+        use std::cell::RefCell;
+        use std::collections::HashMap;
+        use std::rc::Rc;
+        use std::sync::{Arc, Mutex};
+
+        #[test]
+        fn stop_on_low_yield() {
+            let server = mock_discord(|code| code.to_string());
+            let (search, searched) = mock_search(
+                &server,
+                vec![
+                    vec!["aaaa+bbbb", "cccc+dddd"],
+                    vec!["cccc+dddd+eeee+ffff"],
+                    vec!["aaaa+bbbb", "eeee+gggg"],
+                    vec!["aaaa+ffff"],
+                    vec!["hhhh+iiii"],
+                ],
+            );
+            let mut crawler = Crawler::new(Client::new().with_api_base(&server.url))
+                .with_search(search)
+                .with_delay(Duration::from_millis(0))
+                .with_min_yield(0.3, 2);
+
+            let invites = crawler.crawl(10).unwrap();
+            let codes: Vec<&str> = invites.iter().map(|found| found.invite.code.as_str()).collect();
+            assert_eq!(codes, vec!["aaaa", "bbbb", "cccc", "dddd", "eeee", "ffff", "gggg"]);
+            // page 2 yields 1/4 new invites and page 3 none, so page 4 is never loaded
+            assert_eq!(*searched.borrow(), vec![0, 1, 2, 3]);
+            assert!(crawler.is_exhausted());
+            assert_eq!(crawler.next_page(), 4);
+        }
+
+        #[test]
+        fn dry_run() {
+            let server = mock_discord(|code| code.to_string());
+            let client = Client::new().with_api_base(&server.url).with_dry_run(true);
+            let mut crawler = Crawler::new(client).with_delay(Duration::from_millis(0));
+            assert!(crawler.crawl(2).unwrap().is_empty());
+            assert!(server.requests().is_empty());
+            assert_eq!(
+                crawler.client.planned_requests(),
+                vec![SearchQueryBuilder::new().page(0).build(), SearchQueryBuilder::new().page(1).build()]
+            );
+        }
+
+        /// Answers the Google searches with a result page linking to `results`, and sends the other
+        /// requests with the default backend. Every requested url is recorded.
+        #[derive(Debug)]
+        struct GoogleStub {
+            results: Vec<String>,
+            requested: Arc<Mutex<Vec<String>>>,
+        }
+
+        impl HttpClient for GoogleStub {
+            fn get(&self, request: &HttpRequest) -> Result<RawResponse, Error> {
+                self.requested.lock().unwrap().push(request.url.to_string());
+                if !request.url.starts_with("https://www.google.com/search?") {
+                    return crate::backend::default_http_client().get(request);
+                }
+                let results: Vec<String> = self
+                    .results
+                    .iter()
+                    .map(|url| format!(r#"<div class="r"><a href="{}" onmousedown="return rwt(this)">x</a></div>"#, url))
+                    .collect();
+                Ok(RawResponse {
+                    status_code: 200,
+                    headers: HashMap::new(),
+                    url: request.url.to_string(),
+                    body: results.concat().into_bytes(),
+                })
+            }
+        }
+
+        #[test]
+        fn domain_filters() {
+            let server = mock_discord(|code| code.to_string());
+            let requested = Arc::new(Mutex::new(Vec::new()));
+            let stub = GoogleStub {
+                results: vec![format!("{}/aaaa", server.url), "http://spam.example/bbbb".to_string()],
+                requested: Arc::clone(&requested),
+            };
+            let client = Client::new()
+                .with_api_base(&server.url)
+                .with_http_client(stub)
+                .with_deny_domains(&["spam.example"]);
+            let mut crawler = Crawler::new(client).with_delay(Duration::from_millis(0));
+            let codes: Vec<String> = crawler.crawl(1).unwrap().into_iter().map(|found| found.invite.code).collect();
+            assert_eq!(codes, vec!["aaaa"]);
+            assert!(!requested.lock().unwrap().iter().any(|url| url.contains("spam.example")));
+        }
+
+        #[test]
+        fn estimated_requests() {
+            assert_eq!(Crawler::estimate_requests(0, 12.0), 0);
+            assert_eq!(Crawler::estimate_requests(1, 0.0), 11);
+            // 5 searches, 50 results and 25 invites
+            assert_eq!(Crawler::estimate_requests(5, 5.0), 80);
+            assert_eq!(Crawler::estimate_requests(3, 2.5), 41);
+            assert_eq!(Crawler::estimate_requests(2, -1.0), 22);
+        }
+
+        #[test]
+        fn blacklist() {
+            let server = mock_discord(|code| format!("guild-{}", code));
+            let (search, _searched) = mock_search(&server, vec![vec!["aaaa+bbbb", "cccc+dddd"]]);
+            let blacklist: HashSet<String> = vec!["bbbb".to_string(), "guild-cccc".to_string()].into_iter().collect();
+            let mut crawler = Crawler::new(Client::new().with_api_base(&server.url))
+                .with_search(search)
+                .with_delay(Duration::from_millis(0))
+                .with_blacklist(blacklist);
+
+            let invites = crawler.crawl(1).unwrap();
+            let codes: Vec<&str> = invites.iter().map(|found| found.invite.code.as_str()).collect();
+            assert_eq!(codes, vec!["aaaa", "dddd"]);
+            let requests = server.requests();
+            assert!(!requests.iter().any(|request| request.starts_with("GET /v6/invites/bbbb")));
+            assert!(requests.iter().any(|request| request.starts_with("GET /v6/invites/cccc")));
+        }
+
+        #[test]
+        fn continue_on_error() {
+            let server = mock_discord(|code| code.to_string());
+            let (search, _searched) = mock_search(&server, vec![vec!["aaaa+dead+bbbb"], vec!["cccc"]]);
+            let mut crawler = Crawler::new(Client::new().with_api_base(&server.url))
+                .with_search(search)
+                .with_delay(Duration::from_millis(0))
+                .with_error_policy(ErrorPolicy::ContinueAll);
+
+            let invites = crawler.crawl(2).unwrap();
+            let codes: Vec<&str> = invites.iter().map(|found| found.invite.code.as_str()).collect();
+            assert_eq!(codes, vec!["aaaa", "bbbb", "cccc"]);
+        }
+
+        #[test]
+        fn abort_on_error() {
+            let server = mock_discord(|code| code.to_string());
+            let (search, searched) = mock_search(&server, vec![vec!["aaaa+dead+bbbb"], vec!["cccc"]]);
+            let mut crawler = Crawler::new(Client::new().with_api_base(&server.url))
+                .with_search(search)
+                .with_delay(Duration::from_millis(0))
+                .with_error_policy(ErrorPolicy::AbortOnError);
+
+            assert!(matches!(crawler.crawl(2), Err(Error::InvalidResponse)));
+            assert_eq!(*searched.borrow(), vec![0]);
+            assert!(!server.requests().iter().any(|request| request.starts_with("GET /v6/invites/bbbb")));
+        }
+
+        #[test]
+        fn guild_counts() {
+            // guilds are named after the first letter of the codes
+            let server = mock_discord(|code| code[..1].to_string());
+            let (search, _searched) = mock_search(&server, vec![vec!["aaaa+abab+bbbb"], vec!["bcbc+cccc+aaaa"]]);
+            let mut crawler = Crawler::new(Client::new().with_api_base(&server.url))
+                .with_search(search)
+                .with_delay(Duration::from_millis(0));
+
+            let invites = crawler.crawl(2).unwrap();
+            let codes: Vec<&str> = invites.iter().map(|found| found.invite.code.as_str()).collect();
+            assert_eq!(codes, vec!["aaaa", "bbbb", "cccc"]);
+            assert_eq!(crawler.unique_guild_count(), 3);
+            assert_eq!(crawler.total_invites_found(), 5);
+        }
+
+        #[test]
+        fn custom_sink() {
+            struct CountingSink {
+                count: usize,
+                members: u64,
+            }
+
+            impl InviteSink for CountingSink {
+                fn accept(&mut self, invite: Invite) {
+                    self.count += 1;
+                    self.members += invite.member_count_or(0);
+                }
+            }
+
+            let server = mock_discord(|code| code.to_string());
+            let (search, _searched) = mock_search(&server, vec![vec!["aaaa+bbbb"], vec!["bbbb+cccc"]]);
+            let mut crawler = Crawler::new(Client::new().with_api_base(&server.url))
+                .with_search(search)
+                .with_delay(Duration::from_millis(0));
+            let mut sink = CountingSink { count: 0, members: 0 };
+            crawler.crawl_into(2, &mut sink).unwrap();
+            assert_eq!(sink.count, 3);
+            assert_eq!(sink.members, 30);
+        }
+
+        #[test]
+        fn append_only_output() {
+            let path = std::env::temp_dir().join(format!("discord-finder-{}-append.jsonl", std::process::id()));
+            std::fs::write(&path, "{\"code\": \"aaaa\"}\n{\"code\": \"cccc\", \"approximate_member_count\": 10}\n{\"cod").unwrap();
+            let seen = load_seen_codes(&path).unwrap();
+            assert_eq!(seen.len(), 2);
+
+            let server = mock_discord(|code| code.to_string());
+            let (search, _searched) = mock_search(&server, vec![vec!["aaaa+bbbb"], vec!["cccc+dddd"]]);
+            let mut crawler = Crawler::new(Client::new().with_api_base(&server.url))
+                .with_search(search)
+                .with_delay(Duration::from_millis(0));
+            let found = crawler.crawl(2).unwrap();
+            assert_eq!(found.len(), 4);
+            assert_eq!(append_new_invites(&path, &found).unwrap(), 2);
+            assert_eq!(append_new_invites(&path, &found).unwrap(), 0);
+
+            let codes = load_seen_codes(&path).unwrap();
+            std::fs::remove_file(&path).unwrap();
+            let expected: HashSet<String> = ["aaaa", "bbbb", "cccc", "dddd"].iter().map(|code| code.to_string()).collect();
+            assert_eq!(codes, expected);
+            assert!(load_seen_codes(&path).unwrap().is_empty());
+        }
+
+        #[test]
+        fn equivalent_sources() {
+            let server = mock_discord(|code| code.to_string());
+            let page = format!("{}/aaaa", server.url);
+            let mut crawler = Crawler::new(Client::new().with_api_base(&server.url))
+                .with_search(move |_| Ok(vec![page.clone(), format!("{}/", page), format!("{}#invite", page)]))
+                .with_delay(Duration::from_millis(0));
+            assert_eq!(crawler.crawl(1).unwrap().len(), 1);
+            assert_eq!(server.requests().iter().filter(|request| request.starts_with("GET /aaaa")).count(), 1);
+        }
+
+        #[test]
+        fn promising_results_first() {
+            let server = mock_discord(|code| code.to_string());
+            let (search, _searched) = mock_search(&server, vec![vec!["aaaa", "bbbb+serverx", "cccc"]]);
+            let mut crawler = Crawler::new(Client::new().with_api_base(&server.url))
+                .with_search(search)
+                .with_delay(Duration::from_millis(0));
+
+            let invites = crawler.crawl(1).unwrap();
+            let codes: Vec<&str> = invites.iter().map(|found| found.invite.code.as_str()).collect();
+            assert_eq!(codes, vec!["bbbb", "serverx", "aaaa", "cccc"]);
+        }
+
+        #[test]
+        fn discovery_metadata() {
+            let server = mock_discord(|code| code.to_string());
+            let (search, _searched) = mock_search(&server, vec![vec!["aaaa", "bbbb+aaaa"]]);
+            let mut crawler = Crawler::new(Client::new().with_api_base(&server.url))
+                .with_search(search)
+                .with_delay(Duration::from_millis(0));
+
+            let before = SystemTime::now();
+            let invites = crawler.crawl(1).unwrap();
+            assert_eq!(invites.len(), 2);
+            assert_eq!(invites[0].invite.code, "aaaa");
+            assert_eq!(invites[0].source_url, format!("{}/aaaa", server.url));
+            assert_eq!(invites[1].invite.code, "bbbb");
+            assert_eq!(invites[1].source_url, format!("{}/bbbb+aaaa", server.url));
+            for found in &invites {
+                assert!(found.discovered_at >= before && found.discovered_at <= SystemTime::now());
+            }
+        }
+
+        #[test]
+        fn resume_with_seen_codes() {
+            let server = mock_discord(|code| code.to_string());
+            let (search, _searched) = mock_search(&server, vec![vec!["aaaa+bbbb"]]);
+            let mut crawler = Crawler::new(Client::new().with_api_base(&server.url))
+                .with_search(search)
+                .with_delay(Duration::from_millis(0));
+            crawler.crawl(1).unwrap();
+            let seen = crawler.seen_codes().clone();
+            assert_eq!(seen.len(), 2);
+
+            let (search, _searched) = mock_search(&server, vec![vec!["aaaa+cccc+bbbb"]]);
+            let mut crawler = Crawler::new(Client::new().with_api_base(&server.url))
+                .with_search(search)
+                .with_delay(Duration::from_millis(0))
+                .with_seen(seen);
+            let invites = crawler.crawl(1).unwrap();
+            assert_eq!(invites.len(), 1);
+            assert_eq!(invites[0].invite.code, "cccc");
+            assert_eq!(crawler.seen_codes().len(), 3);
+            let fetched = server.requests().iter().filter(|r| r.starts_with("GET /v6/invites/")).count();
+            assert_eq!(fetched, 3);
+        }
+
+        #[test]
+        fn resume_from_checkpoint() {
+            // aaaa and abab lead to the same guild
+            let server = mock_discord(|code| code[..1].to_string());
+            let pages = vec![vec!["aaaa+bbbb"], vec!["abab+cccc+bbbb"]];
+            let (search, _searched) = mock_search(&server, pages.clone());
+            let mut crawler = Crawler::new(Client::new().with_api_base(&server.url))
+                .with_search(search)
+                .with_delay(Duration::from_millis(0));
+            crawler.crawl(1).unwrap();
+            let json = serde_json::to_string(&crawler.checkpoint()).unwrap();
+
+            let state: CrawlState = serde_json::from_str(&json).unwrap();
+            assert_eq!(state, crawler.checkpoint());
+            assert_eq!(state.page, 1);
+            let (search, searched) = mock_search(&server, pages);
+            let mut crawler = Crawler::new(Client::new().with_api_base(&server.url))
+                .with_search(search)
+                .with_delay(Duration::from_millis(0))
+                .resume(state);
+            let invites = crawler.crawl(1).unwrap();
+            let codes: Vec<&str> = invites.iter().map(|found| found.invite.code.as_str()).collect();
+            assert_eq!(codes, vec!["cccc"]);
+            assert_eq!(*searched.borrow(), vec![1]);
+            assert_eq!(crawler.checkpoint().page, 2);
+            assert_eq!(crawler.unique_guild_count(), 3);
+        }
+
+        #[test]
+        fn custom_dedup_key() {
+            let server = MockServer::start(|request| {
+                let path = request.split(' ').nth(1).unwrap_or_default();
+                if let Some(code) = path.strip_prefix("/v6/invites/") {
+                    let code = code.split('?').next().unwrap_or_default();
+                    // two guilds share a name
+                    let (id, name) = match code {
+                        "aaaa" => ("1", "Rust"),
+                        "bbbb" => ("2", "rust "),
+                        _ => ("3", "Go"),
+                    };
+                    let body = format!(
+                        r#"{{"code": "{}", "guild": {{"id": "{}", "name": "{}", "verification_level": 0}}}}"#,
+                        code, id, name
+                    );
+                    http_response(200, &[], body.as_bytes())
+                } else {
+                    http_response(200, &[], b"discord.gg/aaaa discord.gg/bbbb discord.gg/cccc")
+                }
+            });
+            let (search, _searched) = mock_search(&server, vec![vec!["page"]]);
+            let mut crawler = Crawler::new(Client::new().with_api_base(&server.url))
+                .with_search(search)
+                .with_delay(Duration::from_millis(0));
+            assert_eq!(crawler.crawl(1).unwrap().len(), 3);
+
+            let (search, _searched) = mock_search(&server, vec![vec!["page"]]);
+            let mut crawler = Crawler::new(Client::new().with_api_base(&server.url))
+                .with_search(search)
+                .with_delay(Duration::from_millis(0))
+                .with_dedup_key(|invite| invite.guild.as_ref().map(|guild| guild.name().trim().to_lowercase()).unwrap_or_default());
+            let invites = crawler.crawl(1).unwrap();
+            let codes: Vec<&str> = invites.iter().map(|found| found.invite.code.as_str()).collect();
+            assert_eq!(codes, vec!["aaaa", "cccc"]);
+            assert_eq!(crawler.unique_guild_count(), 3);
+            assert_eq!(crawler.checkpoint().seen_keys.len(), 2);
+        }
+
+        #[test]
+        fn crawl_progress() {
+            let server = mock_discord(|code| code.to_string());
+            let (search, _searched) = mock_search(&server, vec![vec!["aaaa"], vec!["aaaa"], vec!["aaaa"], vec!["bbbb"]]);
+            let calls = Rc::new(RefCell::new(Vec::new()));
+            let record = Rc::clone(&calls);
+            let mut crawler = Crawler::new(Client::new().with_api_base(&server.url))
+                .with_search(search)
+                .with_delay(Duration::from_millis(0))
+                .with_min_yield(0.5, 2)
+                .with_progress(move |done, total| record.borrow_mut().push((done, total)));
+
+            crawler.crawl(5).unwrap();
+            // the third page ends the crawl
+            assert_eq!(*calls.borrow(), vec![(1, 5), (2, 5), (3, 5)]);
+        }
+
+        #[test]
+        fn yield_recovers() {
+            let server = mock_discord(|code| code.to_string());
+            let (search, searched) = mock_search(
+                &server,
+                vec![vec!["aaaa"], vec!["aaaa"], vec!["bbbb"], vec!["bbbb"], vec!["cccc"]],
+            );
+            let mut crawler = Crawler::new(Client::new().with_api_base(&server.url))
+                .with_search(search)
+                .with_delay(Duration::from_millis(0))
+                .with_min_yield(0.5, 2);
+
+            assert_eq!(crawler.crawl(5).unwrap().len(), 3);
+            assert_eq!(*searched.borrow(), vec![0, 1, 2, 3, 4]);
+        }
+    }
+}
+
+/// Contains helpers to evaluate the results of a crawl.
+pub mod metrics {
+    use std::collections::HashSet;
+
+    /// Return the fraction of the `reference` codes that were `found`, between 0 and 1.  
+    /// An empty reference set is fully covered.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use discord_finder::metrics::coverage;
+    /// use std::collections::HashSet;
+    ///
+    /// let found: HashSet<String> = vec!["abc".to_string(), "xyz".to_string()].into_iter().collect();
+    /// let reference: HashSet<String> = vec!["abc".to_string(), "def".to_string()].into_iter().collect();
+    /// assert_eq!(coverage(&found, &reference), 0.5);
+    /// ```
+    pub fn coverage(found: &HashSet<String>, reference: &HashSet<String>) -> f64 {
+        if reference.is_empty() {
+            return 1.0;
+        }
+        reference.intersection(found).count() as f64 / reference.len() as f64
+    }
+
+    /// Return the `reference` codes that were not `found`, sorted.
+    pub fn missing(found: &HashSet<String>, reference: &HashSet<String>) -> Vec<String> {
+        let mut missing: Vec<String> = reference.difference(found).cloned().collect();
+        missing.sort();
+        missing
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        fn set(codes: &[&str]) -> HashSet<String> {
+            codes.iter().map(|code| code.to_string()).collect()
+        }
+
+        #[test]
+        fn full_coverage() {
+            let reference = set(&["abc", "def"]);
+            let found = set(&["abc", "def", "ghi"]);
+            assert_eq!(coverage(&found, &reference), 1.0);
+            assert!(missing(&found, &reference).is_empty());
+            assert_eq!(coverage(&found, &set(&[])), 1.0);
+        }
+
+        #[test]
+        fn partial_coverage() {
+            let reference = set(&["abc", "def", "ghi", "jkl"]);
+            let found = set(&["def", "abc", "xyz"]);
+            assert_eq!(coverage(&found, &reference), 0.5);
+            assert_eq!(missing(&found, &reference), vec!["ghi", "jkl"]);
+        }
+
+        #[test]
+        fn zero_coverage() {
+            let reference = set(&["abc", "def"]);
+            assert_eq!(coverage(&set(&[]), &reference), 0.0);
+            assert_eq!(coverage(&set(&["xyz"]), &reference), 0.0);
+            assert_eq!(missing(&set(&["xyz"]), &reference), vec!["abc", "def"]);
+        }
+    }
+}
+
+/// Contains an RSS export of invites. Requires the "feed" feature.
+#[cfg(feature = "feed")]
+pub mod feed {
+    use super::discord::Invite;
+
+    fn escape_xml(text: &str) -> String {
+        let mut escaped = String::with_capacity(text.len());
+        for c in text.chars() {
+            match c {
+                '&' => escaped.push_str("&amp;"),
+                '<' => escaped.push_str("&lt;"),
+                '>' => escaped.push_str("&gt;"),
+                '"' => escaped.push_str("&quot;"),
+                '\'' => escaped.push_str("&apos;"),
+                c => escaped.push(c),
+            }
+        }
+        escaped
+    }
+
+    /// Produce an RSS 2.0 document with one item per invite.  
+    /// The title of an item is the server name (or the code when the invite has no guild),
+    /// its link is the invite url and its description is the member count, omitted when it is unknown.
+    pub fn to_rss(invites: &[Invite]) -> String {
+        let mut rss = String::from(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+             <rss version=\"2.0\">\n\
+             <channel>\n\
+             <title>Discord servers</title>\n\
+             <link>https://discord.com</link>\n\
+             <description>Discord servers found by discord-finder</description>\n",
+        );
+        for invite in invites {
+            let title = match &invite.guild {
+                Some(guild) => guild.name(),
+                None => &invite.code,
+            };
+            let url = invite.get_url();
+            rss.push_str(&format!(
+                "<item>\n\
+                 <title>{}</title>\n\
+                 <link>{}</link>\n\
+                 <guid>{}</guid>\n",
+                escape_xml(title),
+                escape_xml(&url),
+                escape_xml(&url),
+            ));
+            if let Some(counts) = invite.counts_summary() {
+                rss.push_str(&format!("<description>{}</description>\n", counts));
+            }
+            rss.push_str("</item>\n");
+        }
+        rss.push_str("</channel>\n</rss>\n");
+        rss
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn rss_items() {
+            let invites: Vec<Invite> = serde_json::from_str(
+                r#"[{
+                    "code": "UNWEj54",
+                    "guild": {"id": "1", "name": "Cats & Dogs", "verification_level": 0},
+                    "approximate_member_count": 1234,
+                    "approximate_presence_count": 567
+                }, {
+                    "code": "Yyakf3",
+                    "approximate_member_count": 3,
+                    "approximate_presence_count": 0
+                }, {
+                    "code": "8j8b2xR"
+                }]"#,
+            )
+            .unwrap();
+            let rss = to_rss(&invites);
+            assert!(rss.starts_with("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<rss version=\"2.0\">"));
+            assert_eq!(rss.matches("<item>").count(), 3);
+            assert!(rss.contains(
+                "<item>\n\
+                 <title>Cats &amp; Dogs</title>\n\
+                 <link>https://discord.com/invite/UNWEj54</link>\n\
+                 <guid>https://discord.com/invite/UNWEj54</guid>\n\
+                 <description>1234 members (567 online)</description>\n\
+                 </item>"
+            ));
+            assert!(rss.contains("<title>Yyakf3</title>"));
+            assert!(rss.contains(
+                "<item>\n\
+                 <title>8j8b2xR</title>\n\
+                 <link>https://discord.com/invite/8j8b2xR</link>\n\
+                 <guid>https://discord.com/invite/8j8b2xR</guid>\n\
+                 </item>"
+            ));
+            assert!(rss.ends_with("</channel>\n</rss>\n"));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::{mock_discord, mock_search};
+
+    #[test]
+    fn find_servers_end_to_end() {
+        // codes starting with the same letter lead to the same guild
+        let server = mock_discord(|code| code[..1].to_string());
+        let (search, searched) = mock_search(&server, vec![vec!["aaaa+bbbb", "abcd+dead"], vec!["cccc+bbbb"]]);
+        let crawler = crawler::Crawler::new(client::Client::new().with_api_base(&server.url))
+            .with_search(search)
+            .with_delay(Duration::from_millis(0));
+        let invites = find_servers_with(crawler, 3).unwrap();
+        let codes: Vec<&str> = invites.iter().map(|invite| invite.code.as_str()).collect();
+        assert_eq!(codes, vec!["aaaa", "bbbb", "cccc"]);
+        assert!(invites.iter().all(|invite| invite.guild.is_some()));
+        assert_eq!(*searched.borrow(), vec![0, 1, 2]);
+    }
 }